@@ -12,7 +12,9 @@ mod source;
 
 pub use package_id::PackageId;
 pub use report::{
-    Count, CounterBlock, DependencyKind, PackageInfo, QuickReportEntry,
-    QuickSafetyReport, ReportEntry, SafetyReport, UnsafeInfo,
+    Count, CounterBlock, DependencyKind, FunctionMetrics, Hotspot, LineColumn,
+    LintStatus, PackageInfo, QuickReportEntry, QuickSafetyReport, ReportEntry,
+    SafetyCommentCoverage, SafetyReport, UnsafeInfo, UnsafeKind,
+    UnsafeLocation, UnsafeOperationKinds, UnsafeScopeKinds,
 };
 pub use source::Source;
@@ -1,7 +1,7 @@
 use crate::PackageId;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::{Add, AddAssign},
     path::PathBuf,
 };
@@ -43,6 +43,9 @@ pub struct QuickReportEntry {
     pub package: PackageInfo,
     /// Whether this package forbids the use of `unsafe`
     pub forbids_unsafe: bool,
+    /// Level of the `unsafe_code` lint declared by this package
+    #[serde(default)]
+    pub lint_status: LintStatus,
 }
 
 /// Report generated from scanning for packages that forbid the use of `unsafe`
@@ -69,10 +72,22 @@ pub struct ReportEntry {
 pub struct SafetyReport {
     #[serde(with = "entry_serde")]
     pub packages: HashMap<PackageId, ReportEntry>,
+    /// Procedural macro packages, whose code runs inside the compiler at
+    /// build time rather than in the built artifacts, kept out of `packages`
+    #[serde(
+        default,
+        with = "entry_serde",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub proc_macro_packages: HashMap<PackageId, ReportEntry>,
     #[serde(serialize_with = "set_serde::serialize")]
     pub packages_without_metrics: HashSet<PackageId>,
     #[serde(serialize_with = "set_serde::serialize")]
     pub used_but_not_scanned_files: HashSet<PathBuf>,
+    /// The functions with the most unsafe expressions in code used by the
+    /// project, across all packages, only populated when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hotspots: Vec<Hotspot>,
 }
 
 /// Unsafety usage in a package
@@ -82,8 +97,167 @@ pub struct UnsafeInfo {
     pub used: CounterBlock,
     /// Unsafe usage statistics for code not used by the project
     pub unused: CounterBlock,
+    /// Unsafe usage statistics for the build script of this package and the
+    /// files it pulls in, which run at compile time, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_script: Option<CounterBlock>,
+    /// Unsafe usage statistics for the files generated by the build script
+    /// of this package into its `OUT_DIR` and included by the build, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated: Option<CounterBlock>,
+    /// Unsafe usage reported by the `unsafe_code` lint of the compiler, after
+    /// macro expansion and `#[cfg(..)]` evaluation. Only the unsafe counts
+    /// are known. Only populated when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler_lints: Option<CounterBlock>,
+    /// Unsafe usage only found in the macro expanded source of this package,
+    /// in excess of the unsafe usage found in code used by the project. Only
+    /// the unsafe counts are known. Only populated when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macro_expansion_delta: Option<CounterBlock>,
     /// Whether this package forbids the use of `unsafe`
     pub forbids_unsafe: bool,
+    /// Level of the `unsafe_code` lint declared by this package
+    #[serde(default)]
+    pub lint_status: LintStatus,
+    /// Source locations of the `unsafe` usage in code used by the project,
+    /// only populated when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<UnsafeLocation>,
+    /// Coverage of the `unsafe` blocks and `unsafe impl`s in code used by
+    /// the project by `// SAFETY:` comments, only populated when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_comments: Option<SafetyCommentCoverage>,
+    /// Whether some source files of this package could not be parsed, so
+    /// that their `unsafe` usage was counted approximately from their tokens
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub approximate: bool,
+    /// Counts of the custom metrics of the geiger `MetricPlugin`s in code
+    /// used by the project, keyed by plugin name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugin_metrics: BTreeMap<String, u64>,
+}
+
+/// Level of the `unsafe_code` lint declared by a crate, ordered from the
+/// weakest to the strongest guarantee
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub enum LintStatus {
+    /// `unsafe_code` is allowed, which is the default
+    #[default]
+    Allow,
+    /// `#![deny(unsafe_code)]`, with `#[allow(unsafe_code)]` somewhere in
+    /// the crate
+    DenyWithLocalAllow,
+    /// `#![deny(unsafe_code)]` inside a `cfg_attr`, which only applies to
+    /// some configurations
+    ConditionalDeny,
+    /// `#![deny(unsafe_code)]`
+    Deny,
+    /// `#![forbid(unsafe_code)]` inside a `cfg_attr`, which only applies to
+    /// some configurations
+    ConditionalForbid,
+    /// `#![forbid(unsafe_code)]`
+    Forbid,
+}
+
+/// Kind of `unsafe` usage found at an [`UnsafeLocation`]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub enum UnsafeKind {
+    /// An `unsafe fn`, or a function exported with an unsafe attribute
+    Function,
+    /// An `unsafe { .. }` block
+    Block,
+    /// An `unsafe impl`
+    Impl,
+    /// An `unsafe trait`
+    Trait,
+    /// An `unsafe fn` inside an `impl` block
+    Method,
+    /// An `unsafe` block or `unsafe fn` inside the tokens of a macro
+    Macro,
+}
+
+/// Line and column in a source file. Lines are 1-indexed and columns are
+/// 0-indexed, in UTF-8 characters.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Source location of a single `unsafe` usage
+#[derive(
+    Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub struct UnsafeLocation {
+    /// Path to the source file, empty when scanning a string
+    pub file: PathBuf,
+    pub start: LineColumn,
+    pub end: LineColumn,
+    pub kind: UnsafeKind,
+}
+
+/// Unsafe usage inside a single function or method
+#[derive(
+    Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub struct FunctionMetrics {
+    /// Path of the function inside its source file, made of the names of the
+    /// enclosing inline modules, impl types and functions, such as
+    /// `module::Type::method`
+    pub path: String,
+    /// Path to the source file, empty when scanning a string
+    pub file: PathBuf,
+    pub start: LineColumn,
+    pub end: LineColumn,
+    /// Whether the function is unsafe to call or exported with
+    /// `#[no_mangle]` or `#[export_name]`
+    pub is_unsafe: bool,
+    /// Number of expressions in unsafe scopes in the body of the function,
+    /// excluding the ones in nested functions
+    pub unsafe_exprs: u64,
+}
+
+/// A function ranked among the ones with the most unsafe expressions
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Hotspot {
+    pub package: PackageId,
+    pub function: FunctionMetrics,
 }
 
 /// Kind of dependency for a package
@@ -142,15 +316,68 @@ pub struct CounterBlock {
     pub item_impls: Count,
     pub item_traits: Count,
     pub methods: Count,
+    /// Number of `unsafe` blocks and functions found in the tokens of macro
+    /// definitions and invocations. Macro tokens are not parsed, so this is a
+    /// best effort count that is kept apart from the other counters.
+    #[serde(default)]
+    pub unsafe_in_macros: u64,
+    /// Functions declared in `extern` blocks, all of which are unsafe to call
+    #[serde(default)]
+    pub foreign_functions: Count,
+    /// Statics declared in `extern` blocks, all of which are unsafe to access
+    #[serde(default)]
+    pub foreign_statics: Count,
+    /// Rust functions with a non-Rust ABI, such as `extern "C" fn`. The ones
+    /// exported with `#[no_mangle]` or `#[export_name]` count as unsafe.
+    #[serde(default)]
+    pub exported_functions: Count,
+    /// `static` items, where `static mut` items count as unsafe
+    #[serde(default)]
+    pub statics: Count,
+    /// Number of uses of `static mut` items inside unsafe scopes, for the
+    /// items that could be resolved by name within the same file
+    #[serde(default)]
+    pub static_mut_accesses: u64,
+    /// Implementations of `Send` and `Sync`, where `unsafe impl`s count as
+    /// unsafe and negative impls count as safe
+    #[serde(default)]
+    pub send_sync_impls: Count,
+    /// Implementations of other well-known unsafe marker traits such as
+    /// `GlobalAlloc`, `Allocator` or `bytemuck::Pod`
+    #[serde(default)]
+    pub marker_trait_impls: Count,
+    /// Number of `unsafe` blocks, only counted from the diagnostics of the
+    /// `unsafe_code` lint of rustc
+    #[serde(default)]
+    pub unsafe_blocks: u64,
+    /// Breakdown by kind of the operations requiring `unsafe`, when they
+    /// have been classified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsafe_operations: Option<UnsafeOperationKinds>,
+    /// Breakdown of the expressions in unsafe scopes by kind of scope, and
+    /// number of redundant `unsafe` blocks, when they have been counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsafe_scopes: Option<UnsafeScopeKinds>,
+    /// Number of calls to known-dangerous APIs, by the rule they match. Only
+    /// populated when a rule set is given.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dangerous_api_calls: BTreeMap<String, u64>,
 }
 
 impl CounterBlock {
+    /// Whether any `unsafe` usage was counted, leaving out the best effort
+    /// count of `unsafe` in macro tokens
     pub fn has_unsafe(&self) -> bool {
         self.functions.unsafe_ > 0
             || self.exprs.unsafe_ > 0
             || self.item_impls.unsafe_ > 0
             || self.item_traits.unsafe_ > 0
             || self.methods.unsafe_ > 0
+            || self.foreign_functions.unsafe_ > 0
+            || self.foreign_statics.unsafe_ > 0
+            || self.exported_functions.unsafe_ > 0
+            || self.statics.unsafe_ > 0
+            || self.unsafe_blocks > 0
     }
 }
 
@@ -164,6 +391,42 @@ impl Add for CounterBlock {
             item_impls: self.item_impls + other.item_impls,
             item_traits: self.item_traits + other.item_traits,
             methods: self.methods + other.methods,
+            unsafe_in_macros: self.unsafe_in_macros + other.unsafe_in_macros,
+            foreign_functions: self.foreign_functions + other.foreign_functions,
+            foreign_statics: self.foreign_statics + other.foreign_statics,
+            exported_functions: self.exported_functions
+                + other.exported_functions,
+            statics: self.statics + other.statics,
+            static_mut_accesses: self.static_mut_accesses
+                + other.static_mut_accesses,
+            send_sync_impls: self.send_sync_impls + other.send_sync_impls,
+            marker_trait_impls: self.marker_trait_impls
+                + other.marker_trait_impls,
+            unsafe_blocks: self.unsafe_blocks + other.unsafe_blocks,
+            unsafe_operations: match (
+                self.unsafe_operations,
+                other.unsafe_operations,
+            ) {
+                (None, None) => None,
+                (operations, other_operations) => Some(
+                    operations.unwrap_or_default()
+                        + other_operations.unwrap_or_default(),
+                ),
+            },
+            unsafe_scopes: match (self.unsafe_scopes, other.unsafe_scopes) {
+                (None, None) => None,
+                (scopes, other_scopes) => Some(
+                    scopes.unwrap_or_default()
+                        + other_scopes.unwrap_or_default(),
+                ),
+            },
+            dangerous_api_calls: {
+                let mut dangerous_api_calls = self.dangerous_api_calls;
+                for (rule, count) in other.dangerous_api_calls {
+                    *dangerous_api_calls.entry(rule).or_insert(0) += count;
+                }
+                dangerous_api_calls
+            },
         }
     }
 }
@@ -174,6 +437,110 @@ impl AddAssign for CounterBlock {
     }
 }
 
+/// Number of operations requiring `unsafe`, by kind. Types are not resolved,
+/// so operations are recognized from the declarations of the scanned file and
+/// from well-known functions of the standard library.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UnsafeOperationKinds {
+    /// Dereferences of raw pointers
+    pub raw_pointer_derefs: u64,
+    /// Calls by path to functions known to be unsafe, other than `transmute`
+    pub unsafe_fn_calls: u64,
+    /// Reads and writes of union fields
+    pub union_field_accesses: u64,
+    /// Uses of `static mut` items
+    pub static_mut_accesses: u64,
+    /// Uses of the `asm!` and `global_asm!` macros
+    pub inline_asm: u64,
+    /// Calls to `transmute` and `transmute_copy`
+    pub transmutes: u64,
+}
+
+impl Add for UnsafeOperationKinds {
+    type Output = UnsafeOperationKinds;
+
+    fn add(self, other: UnsafeOperationKinds) -> UnsafeOperationKinds {
+        UnsafeOperationKinds {
+            raw_pointer_derefs: self.raw_pointer_derefs
+                + other.raw_pointer_derefs,
+            unsafe_fn_calls: self.unsafe_fn_calls + other.unsafe_fn_calls,
+            union_field_accesses: self.union_field_accesses
+                + other.union_field_accesses,
+            static_mut_accesses: self.static_mut_accesses
+                + other.static_mut_accesses,
+            inline_asm: self.inline_asm + other.inline_asm,
+            transmutes: self.transmutes + other.transmutes,
+        }
+    }
+}
+
+impl AddAssign for UnsafeOperationKinds {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
+/// Number of expressions in unsafe scopes by kind of scope, which matters
+/// under the `unsafe_op_in_unsafe_fn` lint, and number of redundant `unsafe`
+/// blocks
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UnsafeScopeKinds {
+    /// Expressions in the bodies of unsafe functions and methods, outside of
+    /// any `unsafe` block
+    pub implicit_exprs: u64,
+    /// Expressions inside explicit `unsafe` blocks
+    pub explicit_exprs: u64,
+    /// `unsafe` blocks nested inside another `unsafe` block, which have no
+    /// effect
+    pub redundant_blocks: u64,
+}
+
+impl Add for UnsafeScopeKinds {
+    type Output = UnsafeScopeKinds;
+
+    fn add(self, other: UnsafeScopeKinds) -> UnsafeScopeKinds {
+        UnsafeScopeKinds {
+            implicit_exprs: self.implicit_exprs + other.implicit_exprs,
+            explicit_exprs: self.explicit_exprs + other.explicit_exprs,
+            redundant_blocks: self.redundant_blocks + other.redundant_blocks,
+        }
+    }
+}
+
+impl AddAssign for UnsafeScopeKinds {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
+/// Number of `unsafe` blocks and `unsafe impl`s, and how many of them are
+/// documented by a `// SAFETY:` comment, as required by Clippy's
+/// `undocumented_unsafe_blocks` lint
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SafetyCommentCoverage {
+    /// Blocks and impls preceded by a `// SAFETY:` comment
+    pub documented: u64,
+    /// All blocks and impls
+    pub total: u64,
+}
+
+impl Add for SafetyCommentCoverage {
+    type Output = SafetyCommentCoverage;
+
+    fn add(self, other: SafetyCommentCoverage) -> SafetyCommentCoverage {
+        SafetyCommentCoverage {
+            documented: self.documented + other.documented,
+            total: self.total + other.total,
+        }
+    }
+}
+
+impl AddAssign for SafetyCommentCoverage {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
 trait Entry {
     fn package_id(&self) -> &PackageId;
 }
@@ -18,9 +18,12 @@ OPTIONS:
         --features <FEATURES>     Space-separated list of features to activate.
         --all-features            Activate all available features.
         --no-default-features     Do not activate the `default` feature.
-        --target <TARGET>         Set the target triple.
-        --all-targets             Return dependencies for all targets. By
-                                  default only the host target is matched.
+        --target <TARGET>         Set the target triple. Code excluded by
+                                  `#[cfg(..)]` for the target is counted as
+                                  unused.
+        --all-targets             Return dependencies for all targets and scan
+                                  code for all cfgs. By default only the host
+                                  target is matched.
         --manifest-path <PATH>    Path to Cargo.toml.
     -i, --invert                  Invert the tree direction.
         --no-indent               Display the dependencies as a list (rather
@@ -31,6 +34,15 @@ OPTIONS:
                                   been displayed.
     --format <FORMAT>             Format string used for printing dependencies
                                   [default: {p}].
+    --extra-columns               Show the optional counters as extra table
+                                  columns: foreign functions and statics
+                                  declared in extern blocks, functions with
+                                  a non-Rust ABI, statics, static mut
+                                  accesses, impls of Send, Sync and other
+                                  unsafe marker traits, expressions in the
+                                  bodies of unsafe functions, redundant
+                                  unsafe blocks, and unsafe blocks and
+                                  functions found in macro tokens.
     --output-format               Output format for the report: Ascii, GitHubMarkdown,
                                   Json, Utf8, Ratio [default: Utf8]
     --update-readme               Writes output to ./README.md. Looks for a Safety
@@ -49,6 +61,45 @@ OPTIONS:
         --offline                 Run without accessing the network.
     -Z \"<FLAG>...\"                Unstable (nightly-only) flags to Cargo.
         --include-tests           Count unsafe usage in tests.
+        --unsafe-locations        Include the source location of every unsafe
+                                  function, block, impl, trait and method in
+                                  the Json report.
+        --unsafe-operations       Include the number of raw pointer
+                                  dereferences, unsafe function calls, union
+                                  field accesses, static mut accesses, inline
+                                  assembly uses and transmutes in the Json
+                                  report.
+        --unsafe-scopes           Include the number of expressions in the
+                                  bodies of unsafe functions and in explicit
+                                  unsafe blocks, and the number of redundant
+                                  nested unsafe blocks, in the Json report.
+        --dangerous-apis          Count calls to known-dangerous APIs such as
+                                  mem::transmute, adjusted by the
+                                  [dangerous-apis] table of a geiger.toml file
+                                  at the workspace root. Shown in the Json
+                                  report and with --extra-columns.
+        --safety-comments         Include the number of unsafe blocks and
+                                  unsafe impls documented by a `// SAFETY:`
+                                  comment in the Json report.
+        --hotspots <N>            List the N functions with the most
+                                  expressions in unsafe scopes, in code used
+                                  by the build across all packages.
+        --compiler-lints          Also count the unsafe functions, blocks,
+                                  impls, traits and methods reported by the
+                                  unsafe_code lint of rustc during the build,
+                                  after macro expansion. Shown in the Json
+                                  report and as a separate table line.
+                                  The lint is forced to warn, so crates that
+                                  allow it are counted too.
+        --expand-macros           Also scan the macro expanded source of the
+                                  crates during the build, and report the
+                                  unsafe usage only found after expansion, in
+                                  the Json report and as a separate table
+                                  line. Requires a nightly toolchain. Setting
+                                  RUSTC_BOOTSTRAP=1 makes a stable toolchain
+                                  accept it, and every other unstable feature
+                                  for the whole build, cargo-geiger never sets
+                                  it itself.
         --build-dependencies      Also analyze build dependencies.
         --dev-dependencies        Also analyze dev dependencies.
         --all-dependencies        Analyze all dependencies, including build and
@@ -59,6 +110,12 @@ OPTIONS:
                                   significantly faster than the default
                                   scanning mode. TODO: Add ability to combine
                                   this with a whitelist for use in CI.
+        --no-build                Don't build anything, find the used source
+                                  files by following the `mod` declarations
+                                  from the entry points of the crate targets
+                                  instead. Build scripts and procedural macros
+                                  are not run, so generated code is not
+                                  scanned.
     -h, --help                    Prints help information.
     -V, --version                 Prints version information.
 ";
@@ -67,16 +124,22 @@ OPTIONS:
 pub struct Args {
     pub all: bool,
     pub color: Option<String>,
+    pub compiler_lints: bool,
+    pub dangerous_apis: bool,
     pub deps_args: DepsArgs,
+    pub expand_macros: bool,
+    pub extra_columns: bool,
     pub features_args: FeaturesArgs,
     pub forbid_only: bool,
     pub format: String,
     pub frozen: bool,
     pub help: bool,
+    pub hotspots: Option<usize>,
     pub include_tests: bool,
     pub invert: bool,
     pub locked: bool,
     pub manifest_path: Option<PathBuf>,
+    pub no_build: bool,
     pub no_indent: bool,
     pub offline: bool,
     pub output_format: OutputFormat,
@@ -84,7 +147,11 @@ pub struct Args {
     pub prefix_depth: bool,
     pub quiet: bool,
     pub readme_args: ReadmeArgs,
+    pub safety_comments: bool,
     pub target_args: TargetArgs,
+    pub unsafe_locations: bool,
+    pub unsafe_operations: bool,
+    pub unsafe_scopes: bool,
     pub unstable_flags: Vec<String>,
     pub verbosity: Verbosity,
     pub version: bool,
@@ -104,11 +171,15 @@ impl Args {
         let mut args = Args {
             all: raw_args.contains(["-a", "--all"]),
             color: raw_args.opt_value_from_str("--color")?,
+            compiler_lints: raw_args.contains("--compiler-lints"),
+            dangerous_apis: raw_args.contains("--dangerous-apis"),
             deps_args: DepsArgs {
                 all_deps: raw_args.contains("--all-dependencies"),
                 build_deps: raw_args.contains("--build-dependencies"),
                 dev_deps: raw_args.contains("--dev-dependencies"),
             },
+            expand_macros: raw_args.contains("--expand-macros"),
+            extra_columns: raw_args.contains("--extra-columns"),
             features_args: FeaturesArgs {
                 all_features: raw_args.contains("--all-features"),
                 features: parse_features(
@@ -122,10 +193,12 @@ impl Args {
                 .unwrap_or_else(|| "{p}".to_string()),
             frozen: raw_args.contains("--frozen"),
             help: raw_args.contains(["-h", "--help"]),
+            hotspots: raw_args.opt_value_from_str("--hotspots")?,
             include_tests: raw_args.contains("--include-tests"),
             invert: raw_args.contains(["-i", "--invert"]),
             locked: raw_args.contains("--locked"),
             manifest_path: raw_args.opt_value_from_str("--manifest-path")?,
+            no_build: raw_args.contains("--no-build"),
             no_indent: raw_args.contains("--no-indent"),
             offline: raw_args.contains("--offline"),
             package: raw_args.opt_value_from_str(["-p", "--package"])?,
@@ -136,10 +209,14 @@ impl Args {
                 section_name: raw_args.opt_value_from_str("--section-name")?,
                 update_readme: raw_args.contains("--update-readme"),
             },
+            safety_comments: raw_args.contains("--safety-comments"),
             target_args: TargetArgs {
                 all_targets: raw_args.contains("--all-targets"),
                 target: raw_args.opt_value_from_str("--target")?,
             },
+            unsafe_locations: raw_args.contains("--unsafe-locations"),
+            unsafe_operations: raw_args.contains("--unsafe-operations"),
+            unsafe_scopes: raw_args.contains("--unsafe-scopes"),
             unstable_flags: raw_args
                 .opt_value_from_str("-Z")?
                 .map(|s: String| s.split(' ').map(|s| s.to_owned()).collect())
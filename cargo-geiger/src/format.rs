@@ -49,6 +49,7 @@ impl FromStr for Charset {
 #[derive(Debug, Clone, EnumIter, Eq, PartialEq)]
 pub enum CrateDetectionStatus {
     NoneDetectedForbidsUnsafe,
+    NoneDetectedDeniesUnsafe,
     NoneDetectedAllowsUnsafe,
     UnsafeDetected,
 }
@@ -65,6 +66,7 @@ pub enum SymbolKind {
     Lock = 0,
     QuestionMark = 1,
     Rads = 2,
+    NoEntry = 3,
 }
 
 #[derive(Debug)]
@@ -38,6 +38,9 @@ pub struct PrintConfig {
     pub allow_partial_results: bool,
     pub direction: EdgeDirection,
 
+    /// Show the optional counters as extra table columns.
+    pub extra_columns: bool,
+
     // Is anyone using this? This is a carry-over from cargo-tree.
     // TODO: Open a github issue to discuss deprecation.
     pub format: Pattern,
@@ -82,6 +85,7 @@ impl PrintConfig {
             all: args.all,
             allow_partial_results,
             direction,
+            extra_columns: args.extra_columns,
             format,
             include_tests,
             output_format: args.output_format,
@@ -96,6 +100,7 @@ impl Default for PrintConfig {
             all: false,
             allow_partial_results: false,
             direction: Direction::Outgoing,
+            extra_columns: false,
             format: Pattern::try_build("p").unwrap(),
             include_tests: IncludeTests::Yes,
             prefix: Prefix::Depth,
@@ -113,6 +118,7 @@ pub fn colorize(
         OutputFormat::GitHubMarkdown => ColoredString::from(string.as_str()),
         _ => match crate_detection_status {
             CrateDetectionStatus::NoneDetectedForbidsUnsafe => string.green(),
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe => string.cyan(),
             CrateDetectionStatus::NoneDetectedAllowsUnsafe => string.normal(),
             CrateDetectionStatus::UnsafeDetected => string.red().bold(),
         },
@@ -265,6 +271,11 @@ mod print_config_tests {
             OutputFormat::Ascii,
             String::from("string_value").green()
         ),
+        case(
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe,
+            OutputFormat::Ascii,
+            String::from("string_value").cyan()
+        ),
         case(
             CrateDetectionStatus::NoneDetectedAllowsUnsafe,
             OutputFormat::Utf8,
@@ -14,7 +14,7 @@ use handle_text_tree_line::{
 };
 use total_package_counts::TotalPackageCounts;
 
-use cargo_geiger_serde::{Count, CounterBlock};
+use cargo_geiger_serde::{Count, CounterBlock, SafetyCommentCoverage};
 use colored::ColoredString;
 use std::collections::HashSet;
 use std::path::PathBuf;
@@ -31,6 +31,106 @@ pub const UNSAFE_COUNTERS_HEADER: [&str; 6] = [
     "Dependency",
 ];
 
+/// Headers of the optional columns, shown between the default unsafe counter
+/// columns and the dependency column when `--extra-columns` is used.
+pub const EXTRA_COUNTERS_HEADER: [&str; 11] = [
+    "Foreign Fns ",
+    "Foreign Statics ",
+    "Exported Fns ",
+    "Statics ",
+    "Static Mut Accesses ",
+    "Send/Sync ",
+    "Marker Impls ",
+    "Dangerous Calls ",
+    "Implicit Exprs ",
+    "Redundant Blocks ",
+    "Unsafe In Macros ",
+];
+
+/// Header of the optional column showing how many of the `unsafe` blocks and
+/// impls in used code are documented by a `// SAFETY:` comment, shown after
+/// the other optional columns.
+pub const SAFETY_COMMENTS_HEADER: &str = "Safety Comments ";
+
+/// Formats the counters shown in the optional columns, in the same order as
+/// `EXTRA_COUNTERS_HEADER`. Counters of safe and unsafe items are formatted
+/// with `fmt` like the default columns, plain numbers as `used/total` whatever
+/// the output format, such as the calls to dangerous APIs, the expressions in
+/// the bodies of unsafe functions, the redundant `unsafe` blocks and the
+/// best effort count of `unsafe` in macro tokens.
+fn extra_counters<F>(
+    used: &CounterBlock,
+    not_used: &CounterBlock,
+    fmt: F,
+) -> [String; 11]
+where
+    F: Fn(&Count, &Count) -> String,
+{
+    let number =
+        |used: u64, not_used: u64| format!("{}/{}", used, used + not_used);
+    let dangerous_api_calls = |counter_block: &CounterBlock| -> u64 {
+        counter_block.dangerous_api_calls.values().sum()
+    };
+    let used_scopes = used.unsafe_scopes.clone().unwrap_or_default();
+    let not_used_scopes = not_used.unsafe_scopes.clone().unwrap_or_default();
+    [
+        fmt(&used.foreign_functions, &not_used.foreign_functions),
+        fmt(&used.foreign_statics, &not_used.foreign_statics),
+        fmt(&used.exported_functions, &not_used.exported_functions),
+        fmt(&used.statics, &not_used.statics),
+        number(used.static_mut_accesses, not_used.static_mut_accesses),
+        fmt(&used.send_sync_impls, &not_used.send_sync_impls),
+        fmt(&used.marker_trait_impls, &not_used.marker_trait_impls),
+        number(dangerous_api_calls(used), dangerous_api_calls(not_used)),
+        number(used_scopes.implicit_exprs, not_used_scopes.implicit_exprs),
+        number(
+            used_scopes.redundant_blocks,
+            not_used_scopes.redundant_blocks,
+        ),
+        number(used.unsafe_in_macros, not_used.unsafe_in_macros),
+    ]
+}
+
+/// Formats the optional columns, each aligned with its header in the same
+/// way as the last of the default columns.
+fn extra_columns<F>(
+    used: &CounterBlock,
+    not_used: &CounterBlock,
+    fmt: F,
+) -> String
+where
+    F: Fn(&Count, &Count) -> String,
+{
+    EXTRA_COUNTERS_HEADER
+        .iter()
+        .zip(extra_counters(used, not_used, fmt))
+        .map(|(header, column)| format!("  {: <1$}", column, header.len() - 1))
+        .collect()
+}
+
+/// Formats the optional safety comment column, as `documented/total` or as
+/// the documented ratio.
+fn safety_comments_column(
+    safety_comments: &SafetyCommentCoverage,
+    output_format: OutputFormat,
+) -> String {
+    let SafetyCommentCoverage { documented, total } = *safety_comments;
+    let coverage = match output_format {
+        OutputFormat::Ratio => format!(
+            "{:>5}/{:<}={:.2}%",
+            documented,
+            total,
+            if total == 0 {
+                100.0
+            } else {
+                (100.00 * documented as f32) / (total as f32)
+            }
+        ),
+        _ => format!("{}/{}", documented, total),
+    };
+    format!("  {: <1$}", coverage, SAFETY_COMMENTS_HEADER.len() - 1)
+}
+
 pub fn create_table_from_text_tree_lines(
     cargo_metadata_parameters: &CargoMetadataParameters,
     table_parameters: &TableParameters,
@@ -48,14 +148,18 @@ pub fn create_table_from_text_tree_lines(
         warning_count: &mut warning_count,
     };
 
-    for table_line in text_tree_lines.into_iter().filter_map(|text_tree_line| {
+    for table_line in text_tree_lines.into_iter().flat_map(|text_tree_line| {
         match text_tree_line {
             TextTreeLine::ExtraDepsGroup {
                 kind: dep_kind,
                 tree_vines,
             } => text_tree_line_extra_deps_group_to_table_line_string(
-                dep_kind, tree_vines,
-            ),
+                dep_kind,
+                table_parameters.print_config.extra_columns,
+                tree_vines,
+            )
+            .into_iter()
+            .collect(),
             TextTreeLine::Package {
                 id: package_id,
                 tree_vines,
@@ -81,8 +185,10 @@ pub fn create_table_from_text_tree_lines(
         table_footer(
             total_package_counts.total_counter_block,
             total_package_counts.total_unused_counter_block,
+            &total_package_counts.total_safety_comments,
             table_parameters.print_config.output_format,
-            total_detection_status
+            total_detection_status,
+            table_parameters.print_config.extra_columns,
         )
     ));
 
@@ -103,13 +209,15 @@ pub struct TableParameters<'a> {
 fn table_footer_unsafe_counts(
     used: CounterBlock,
     not_used: CounterBlock,
+    safety_comments: &SafetyCommentCoverage,
     output_format: OutputFormat,
     status: CrateDetectionStatus,
+    show_extra_columns: bool,
 ) -> ColoredString {
     let fmt = |used: &Count, not_used: &Count| {
         format!("{}/{}", used.unsafe_, used.unsafe_ + not_used.unsafe_)
     };
-    let output = format!(
+    let mut output = format!(
         "{: <10} {: <12} {: <6} {: <7} {: <7}",
         fmt(&used.functions, &not_used.functions),
         fmt(&used.exprs, &not_used.exprs),
@@ -117,14 +225,21 @@ fn table_footer_unsafe_counts(
         fmt(&used.item_traits, &not_used.item_traits),
         fmt(&used.methods, &not_used.methods),
     );
+    if show_extra_columns {
+        output.push_str(&extra_columns(&used, &not_used, fmt));
+        output
+            .push_str(&safety_comments_column(safety_comments, output_format));
+    }
     colorize(&status, output_format, output)
 }
 
 fn table_footer_safe_ratio(
     used: CounterBlock,
     not_used: CounterBlock,
+    safety_comments: &SafetyCommentCoverage,
     output_format: OutputFormat,
     status: CrateDetectionStatus,
+    show_extra_columns: bool,
 ) -> ColoredString {
     let fmt = |used: &Count, not_used: &Count| {
         format!(
@@ -143,7 +258,7 @@ fn table_footer_safe_ratio(
             }
         )
     };
-    let output = format!(
+    let mut output = format!(
         "{: <12} {: <18} {: <18} {: <12} {: <12}",
         fmt(&used.functions, &not_used.functions),
         fmt(&used.exprs, &not_used.exprs),
@@ -151,27 +266,48 @@ fn table_footer_safe_ratio(
         fmt(&used.item_traits, &not_used.item_traits),
         fmt(&used.methods, &not_used.methods),
     );
+    if show_extra_columns {
+        output.push_str(&extra_columns(&used, &not_used, fmt));
+        output
+            .push_str(&safety_comments_column(safety_comments, output_format));
+    }
     colorize(&status, output_format, output)
 }
 
 fn table_footer(
     used: CounterBlock,
     not_used: CounterBlock,
+    safety_comments: &SafetyCommentCoverage,
     output_format: OutputFormat,
     status: CrateDetectionStatus,
+    show_extra_columns: bool,
 ) -> ColoredString {
     match output_format {
-        OutputFormat::Ratio => {
-            table_footer_safe_ratio(used, not_used, output_format, status)
-        }
-        _ => table_footer_unsafe_counts(used, not_used, output_format, status),
+        OutputFormat::Ratio => table_footer_safe_ratio(
+            used,
+            not_used,
+            safety_comments,
+            output_format,
+            status,
+            show_extra_columns,
+        ),
+        _ => table_footer_unsafe_counts(
+            used,
+            not_used,
+            safety_comments,
+            output_format,
+            status,
+            show_extra_columns,
+        ),
     }
 }
 
 fn table_row(
     used: &CounterBlock,
     not_used: &CounterBlock,
+    safety_comments: &SafetyCommentCoverage,
     output_format: OutputFormat,
+    show_extra_columns: bool,
 ) -> String {
     match output_format {
         OutputFormat::Ratio => {
@@ -201,41 +337,64 @@ fn table_row(
                     }
                 )
             };
-            format!(
+            let mut row = format!(
                 "{: <12} {: <18} {: <18} {: <12} {: <12}",
                 fmt(&used.functions, &not_used.functions),
                 fmt(&used.exprs, &not_used.exprs),
                 fmt(&used.item_impls, &not_used.item_impls),
                 fmt(&used.item_traits, &not_used.item_traits),
                 fmt(&used.methods, &not_used.methods)
-            )
+            );
+            if show_extra_columns {
+                row.push_str(&extra_columns(used, not_used, fmt));
+                row.push_str(&safety_comments_column(
+                    safety_comments,
+                    output_format,
+                ));
+            }
+            row
         }
         _ => {
             let fmt = |used: &Count, not_used: &Count| {
                 format!("{}/{}", used.unsafe_, used.unsafe_ + not_used.unsafe_)
             };
-            format!(
+            let mut row = format!(
                 "{: <10} {: <12} {: <6} {: <7} {: <7}",
                 fmt(&used.functions, &not_used.functions),
                 fmt(&used.exprs, &not_used.exprs),
                 fmt(&used.item_impls, &not_used.item_impls),
                 fmt(&used.item_traits, &not_used.item_traits),
                 fmt(&used.methods, &not_used.methods)
-            )
+            );
+            if show_extra_columns {
+                row.push_str(&extra_columns(used, not_used, fmt));
+                row.push_str(&safety_comments_column(
+                    safety_comments,
+                    output_format,
+                ));
+            }
+            row
         }
     }
 }
 
-fn table_row_empty() -> String {
+fn table_row_empty(show_extra_columns: bool) -> String {
     let headers_but_last =
         &UNSAFE_COUNTERS_HEADER[..UNSAFE_COUNTERS_HEADER.len() - 1];
-    let n = headers_but_last
+    let mut n = headers_but_last
         .iter()
         .map(|s| s.len())
         .sum::<usize>()
         + headers_but_last.len() + 4// Space after each column
         + 2 // Unsafety symbol width
         + 1; // Space after symbol
+    if show_extra_columns {
+        n += EXTRA_COUNTERS_HEADER
+            .iter()
+            .chain(&[SAFETY_COMMENTS_HEADER])
+            .map(|s| s.len() + 1) // Space after each column
+            .sum::<usize>();
+    }
     " ".repeat(n)
 }
 
@@ -245,6 +404,7 @@ mod table_tests {
 
     use crate::scan::{unsafe_stats, PackageMetrics, RsFileMetricsWrapper};
 
+    use cargo_geiger_serde::UnsafeScopeKinds;
     use geiger::RsFileMetrics;
     use rstest::*;
     use std::collections::HashMap;
@@ -282,8 +442,10 @@ mod table_tests {
             let table_footer = table_footer(
                 used_counter_block.clone(),
                 not_used_counter_block.clone(),
+                &SafetyCommentCoverage::default(),
                 input_output_format,
                 crate_detection_status.clone(),
+                false,
             );
 
             assert_eq!(
@@ -317,7 +479,10 @@ mod table_tests {
             create_rs_file_metrics_wrapper(false, false),
         );
 
-        let package_metrics = PackageMetrics { rs_path_to_metrics };
+        let package_metrics = PackageMetrics {
+            rs_path_to_metrics,
+            ..Default::default()
+        };
         let rs_files_used: HashSet<PathBuf> = [
             Path::new("package_1_path").to_path_buf(),
             Path::new("package_3_path").to_path_buf(),
@@ -327,38 +492,79 @@ mod table_tests {
         .collect();
         let unsafety = unsafe_stats(&package_metrics, &rs_files_used);
 
-        let table_row =
-            table_row(&unsafety.used, &unsafety.unused, OutputFormat::Ascii);
+        let table_row = table_row(
+            &unsafety.used,
+            &unsafety.unused,
+            &unsafety.safety_comments.unwrap_or_default(),
+            OutputFormat::Ascii,
+            false,
+        );
         assert_eq!(table_row, "4/6        8/12         12/18  16/24   20/30  ");
     }
 
+    #[rstest(
+        input_output_format,
+        expected_row,
+        case(
+            OutputFormat::Ascii,
+            String::from("2/4        4/8          6/12   8/16    10/20    14/28        16/32            18/36         20/40    21/42                24/48      26/52         27/54            28/56           30/60             11/22             3/4            ")
+        ),
+        case(
+            OutputFormat::Ratio,
+            String::from("    2/6=33.33%     6/14=42.86%       10/22=45.45%       14/30=46.67%    18/38=47.37%     26/54=48.15%     30/62=48.39%     34/70=48.57%     38/78=48.72%  21/42                   46/94=48.94%     50/102=49.02%  27/54            28/56           30/60             11/22                 3/4=75.00% ")
+        )
+    )]
+    fn table_row_test_extra_columns(
+        input_output_format: OutputFormat,
+        expected_row: String,
+    ) {
+        let table_row = table_row(
+            &create_counter_block(),
+            &create_counter_block(),
+            &SafetyCommentCoverage {
+                documented: 3,
+                total: 4,
+            },
+            input_output_format,
+            true,
+        );
+        assert_eq!(table_row, expected_row);
+    }
+
     #[rstest]
     fn table_row_empty_test() {
-        let empty_table_row = table_row_empty();
+        let empty_table_row = table_row_empty(false);
         assert_eq!(empty_table_row.len(), 55);
+        let empty_table_row = table_row_empty(true);
+        assert_eq!(empty_table_row.len(), 240);
     }
 
     #[rstest(
         input_none_detected_forbids_unsafe,
+        input_none_detected_denies_unsafe,
         input_none_detected_allows_unsafe,
         input_unsafe_detected,
         expected_crate_detection_status,
-        case(0, 0, 1, CrateDetectionStatus::UnsafeDetected),
-        case(1, 0, 0, CrateDetectionStatus::NoneDetectedForbidsUnsafe),
-        case(4, 1, 0, CrateDetectionStatus::NoneDetectedAllowsUnsafe)
+        case(0, 0, 0, 1, CrateDetectionStatus::UnsafeDetected),
+        case(1, 0, 0, 0, CrateDetectionStatus::NoneDetectedForbidsUnsafe),
+        case(1, 2, 0, 0, CrateDetectionStatus::NoneDetectedDeniesUnsafe),
+        case(4, 2, 1, 0, CrateDetectionStatus::NoneDetectedAllowsUnsafe)
     )]
     fn total_package_counts_get_total_detection_status_tests(
         input_none_detected_forbids_unsafe: i32,
+        input_none_detected_denies_unsafe: i32,
         input_none_detected_allows_unsafe: i32,
         input_unsafe_detected: i32,
         expected_crate_detection_status: CrateDetectionStatus,
     ) {
         let total_detection_status = TotalPackageCounts {
             none_detected_forbids_unsafe: input_none_detected_forbids_unsafe,
+            none_detected_denies_unsafe: input_none_detected_denies_unsafe,
             none_detected_allows_unsafe: input_none_detected_allows_unsafe,
             unsafe_detected: input_unsafe_detected,
             total_counter_block: CounterBlock::default(),
             total_unused_counter_block: CounterBlock::default(),
+            total_safety_comments: SafetyCommentCoverage::default(),
         };
 
         assert_eq!(
@@ -375,8 +581,12 @@ mod table_tests {
             metrics: RsFileMetrics {
                 counters: create_counter_block(),
                 forbids_unsafe,
+                ..Default::default()
             },
             is_crate_entry_point,
+            is_build_script: false,
+            is_shared_with_crate: false,
+            is_generated: false,
         }
     }
 
@@ -402,6 +612,42 @@ mod table_tests {
                 safe: 9,
                 unsafe_: 10,
             },
+            unsafe_in_macros: 11,
+            foreign_functions: Count {
+                safe: 13,
+                unsafe_: 14,
+            },
+            foreign_statics: Count {
+                safe: 15,
+                unsafe_: 16,
+            },
+            exported_functions: Count {
+                safe: 17,
+                unsafe_: 18,
+            },
+            statics: Count {
+                safe: 19,
+                unsafe_: 20,
+            },
+            static_mut_accesses: 21,
+            send_sync_impls: Count {
+                safe: 23,
+                unsafe_: 24,
+            },
+            marker_trait_impls: Count {
+                safe: 25,
+                unsafe_: 26,
+            },
+            unsafe_blocks: 27,
+            unsafe_operations: None,
+            unsafe_scopes: Some(UnsafeScopeKinds {
+                implicit_exprs: 28,
+                explicit_exprs: 29,
+                redundant_blocks: 30,
+            }),
+            dangerous_api_calls: vec![(String::from("mem::transmute"), 27)]
+                .into_iter()
+                .collect(),
         }
     }
 }
@@ -1,13 +1,16 @@
 use crate::format::emoji_symbols::EmojiSymbols;
 use crate::format::print_config::{colorize, OutputFormat};
 use crate::format::{get_kind_group_name, CrateDetectionStatus, SymbolKind};
-use crate::mapping::CargoMetadataParameters;
+use crate::mapping::{CargoMetadataParameters, IsProcMacro};
 use crate::scan::unsafe_stats;
 
 use super::total_package_counts::TotalPackageCounts;
 use super::TableParameters;
 use super::{table_row, table_row_empty};
 
+use cargo_geiger_serde::{
+    CounterBlock, LintStatus, SafetyCommentCoverage, UnsafeInfo,
+};
 use cargo_metadata::{DependencyKind, PackageId};
 use colored::ColoredString;
 use std::collections::HashSet;
@@ -21,10 +24,17 @@ pub struct HandlePackageParameters<'a> {
 
 pub fn text_tree_line_extra_deps_group_to_table_line_string(
     dep_kind: DependencyKind,
+    show_extra_columns: bool,
     tree_vines: String,
 ) -> Option<String> {
-    get_kind_group_name(dep_kind)
-        .map(|name| format!("{}{}{}", table_row_empty(), tree_vines, name,))
+    get_kind_group_name(dep_kind).map(|name| {
+        format!(
+            "{}{}{}",
+            table_row_empty(show_extra_columns),
+            tree_vines,
+            name,
+        )
+    })
 }
 
 pub fn text_tree_line_package_to_table_line_string(
@@ -34,7 +44,7 @@ pub fn text_tree_line_package_to_table_line_string(
     package_id: PackageId,
     table_parameters: &TableParameters,
     tree_vines: String,
-) -> Option<String> {
+) -> Vec<String> {
     let package_is_new = handle_package_parameters
         .visited_package_ids
         .insert(package_id.clone());
@@ -48,72 +58,170 @@ pub fn text_tree_line_package_to_table_line_string(
         None => {
             *handle_package_parameters.warning_count += package_is_new as u64;
             eprintln!("WARNING: No metrics found for package: {}", package_id);
-            return None;
+            return Vec::new();
         }
     };
     let unsafe_info =
         unsafe_stats(package_metrics, table_parameters.rs_files_used);
-    if package_is_new {
+    // Procedural macros run inside the compiler, like build scripts their
+    // unsafe usage is not included in the totals
+    let is_proc_macro = cargo_metadata_parameters
+        .metadata
+        .is_proc_macro(&package_id);
+    if package_is_new && !is_proc_macro {
+        // Generated files are compiled into the package like its hand
+        // written code
         handle_package_parameters
             .total_package_counts
-            .total_counter_block += unsafe_info.used.clone();
+            .total_counter_block += unsafe_info.used.clone()
+            + unsafe_info.generated.clone().unwrap_or_default();
         handle_package_parameters
             .total_package_counts
             .total_unused_counter_block += unsafe_info.unused.clone();
+        handle_package_parameters
+            .total_package_counts
+            .total_safety_comments +=
+            unsafe_info.safety_comments.clone().unwrap_or_default();
     }
-    let unsafe_found = unsafe_info.used.has_unsafe();
-    let crate_forbids_unsafe = unsafe_info.forbids_unsafe;
-    let total_inc = package_is_new as i32;
+    let unsafe_found = package_has_unsafe(&unsafe_info);
+    // Proc-macro packages are left out of the footer package counts as well
+    let total_inc = (package_is_new && !is_proc_macro) as i32;
     let crate_detection_status =
         get_crate_detection_status_and_update_package_counts(
-            crate_forbids_unsafe,
+            unsafe_info.lint_status,
             handle_package_parameters,
             total_inc,
             unsafe_found,
         );
 
-    let icon = match crate_detection_status {
-        CrateDetectionStatus::NoneDetectedForbidsUnsafe => {
-            emoji_symbols.emoji(SymbolKind::Lock)
-        }
-        CrateDetectionStatus::NoneDetectedAllowsUnsafe => {
-            emoji_symbols.emoji(SymbolKind::QuestionMark)
-        }
-        CrateDetectionStatus::UnsafeDetected => {
-            emoji_symbols.emoji(SymbolKind::Rads)
-        }
-    };
-
+    let icon = detection_status_icon(emoji_symbols, &crate_detection_status);
+    let package_display = format!(
+        "{}",
+        table_parameters
+            .print_config
+            .format
+            .display(cargo_metadata_parameters, &package_id)
+    );
     let package_name = colorize(
         &crate_detection_status,
         table_parameters.print_config.output_format,
-        format!(
-            "{}",
-            table_parameters
-                .print_config
-                .format
-                .display(cargo_metadata_parameters, &package_id)
-        ),
+        if is_proc_macro {
+            format!("{} [proc-macro]", package_display)
+        } else {
+            package_display.clone()
+        },
     );
+    let build_script = unsafe_info.build_script.clone();
+    let generated = unsafe_info.generated.clone();
+    let compiler_lints = unsafe_info.compiler_lints.clone();
+    let macro_expansion_delta = unsafe_info.macro_expansion_delta.clone();
     let unsafe_info = colorize(
         &crate_detection_status,
         table_parameters.print_config.output_format,
         table_row(
             &unsafe_info.used,
             &unsafe_info.unused,
+            &unsafe_info.safety_comments.unwrap_or_default(),
             table_parameters.print_config.output_format,
+            table_parameters.print_config.extra_columns,
         ),
     );
 
-    Some(construct_package_text_tree_line(
+    let mut lines = vec![construct_package_text_tree_line(
         crate_detection_status,
         emoji_symbols,
         icon,
         package_name,
         table_parameters,
+        tree_vines.clone(),
+        unsafe_info,
+    )];
+    for (label, counter_block) in [
+        ("build script", build_script),
+        ("generated", generated),
+        ("compiler lints", compiler_lints),
+        ("macro expansion", macro_expansion_delta),
+    ] {
+        if let Some(counter_block) = counter_block {
+            lines.push(construct_counter_block_text_tree_line(
+                &counter_block,
+                emoji_symbols,
+                label,
+                &package_display,
+                table_parameters,
+                tree_vines.clone(),
+            ));
+        }
+    }
+    lines
+}
+
+/// The line of a part of a package reported separately, like its build
+/// script or its generated files, or of its counts from another source,
+/// labelled after the package name. Build scripts run at compile time, their
+/// unsafe usage is not included in the totals.
+fn construct_counter_block_text_tree_line(
+    counter_block: &CounterBlock,
+    emoji_symbols: &EmojiSymbols,
+    label: &str,
+    package_display: &str,
+    table_parameters: &TableParameters,
+    tree_vines: String,
+) -> String {
+    let output_format = table_parameters.print_config.output_format;
+    let crate_detection_status = if counter_block.has_unsafe() {
+        CrateDetectionStatus::UnsafeDetected
+    } else {
+        CrateDetectionStatus::NoneDetectedAllowsUnsafe
+    };
+    let name = colorize(
+        &crate_detection_status,
+        output_format,
+        format!("{} [{}]", package_display, label),
+    );
+    let unsafe_info = colorize(
+        &crate_detection_status,
+        output_format,
+        table_row(
+            counter_block,
+            &CounterBlock::default(),
+            &SafetyCommentCoverage::default(),
+            output_format,
+            table_parameters.print_config.extra_columns,
+        ),
+    );
+
+    let icon = detection_status_icon(emoji_symbols, &crate_detection_status);
+
+    construct_package_text_tree_line(
+        crate_detection_status,
+        emoji_symbols,
+        icon,
+        name,
+        table_parameters,
         tree_vines,
         unsafe_info,
-    ))
+    )
+}
+
+fn detection_status_icon(
+    emoji_symbols: &EmojiSymbols,
+    crate_detection_status: &CrateDetectionStatus,
+) -> Box<dyn Display> {
+    match crate_detection_status {
+        CrateDetectionStatus::NoneDetectedForbidsUnsafe => {
+            emoji_symbols.emoji(SymbolKind::Lock)
+        }
+        CrateDetectionStatus::NoneDetectedDeniesUnsafe => {
+            emoji_symbols.emoji(SymbolKind::NoEntry)
+        }
+        CrateDetectionStatus::NoneDetectedAllowsUnsafe => {
+            emoji_symbols.emoji(SymbolKind::QuestionMark)
+        }
+        CrateDetectionStatus::UnsafeDetected => {
+            emoji_symbols.emoji(SymbolKind::Rads)
+        }
+    }
 }
 
 fn construct_package_text_tree_line(
@@ -163,20 +271,41 @@ fn construct_package_text_tree_line(
     format!("{} {}{}", line, tree_vines, package_name)
 }
 
+/// Whether `unsafe` usage was found in the code compiled into the package,
+/// its hand written files or the files generated by its build script.
+fn package_has_unsafe(unsafe_info: &UnsafeInfo) -> bool {
+    unsafe_info.used.has_unsafe()
+        || unsafe_info
+            .generated
+            .as_ref()
+            .is_some_and(|generated| generated.has_unsafe())
+}
+
 fn get_crate_detection_status_and_update_package_counts(
-    crate_forbids_unsafe: bool,
+    crate_lint_status: LintStatus,
     handle_package_parameters: &mut HandlePackageParameters,
     total_inc: i32,
     unsafe_found: bool,
 ) -> CrateDetectionStatus {
-    match (crate_forbids_unsafe, unsafe_found) {
-        (true, false) => {
+    match (crate_lint_status, unsafe_found) {
+        (LintStatus::Forbid, false) => {
             handle_package_parameters
                 .total_package_counts
                 .none_detected_forbids_unsafe += total_inc;
             CrateDetectionStatus::NoneDetectedForbidsUnsafe
         }
-        (false, false) => {
+        (
+            LintStatus::Deny
+            | LintStatus::ConditionalDeny
+            | LintStatus::ConditionalForbid,
+            false,
+        ) => {
+            handle_package_parameters
+                .total_package_counts
+                .none_detected_denies_unsafe += total_inc;
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe
+        }
+        (_, false) => {
             handle_package_parameters
                 .total_package_counts
                 .none_detected_allows_unsafe += total_inc;
@@ -196,19 +325,24 @@ mod handle_text_tree_line_tests {
     use super::*;
 
     use crate::format::print_config::PrintConfig;
+    use crate::lib_tests::construct_krates_and_metadata;
+    use crate::scan::{GeigerContext, PackageMetrics, RsFileMetricsWrapper};
+    use cargo_geiger_serde::Count;
     use colored::Colorize;
     use rstest::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
 
     #[rstest(
         input_dep_kind,
         expected_table_line_option,
         case(
             DependencyKind::Build,
-            Some(format!("{}{}{}", table_row_empty(), "tree_vines", "[build-dependencies]"))
+            Some(format!("{}{}{}", table_row_empty(false), "tree_vines", "[build-dependencies]"))
         ),
         case(
             DependencyKind::Development,
-            Some(format!("{}{}{}", table_row_empty(), "tree_vines", "[dev-dependencies]"))
+            Some(format!("{}{}{}", table_row_empty(false), "tree_vines", "[dev-dependencies]"))
         ),
         case(DependencyKind::Normal, None)
     )]
@@ -220,6 +354,7 @@ mod handle_text_tree_line_tests {
         let actual_table_lines =
             text_tree_line_extra_deps_group_to_table_line_string(
                 input_dep_kind,
+                false,
                 tree_vines,
             );
 
@@ -237,6 +372,12 @@ mod handle_text_tree_line_tests {
             SymbolKind::Lock,
             String::from("unsafe_info  🔒  tree_vinespackage_name")
         ),
+        case(
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe,
+            OutputFormat::GitHubMarkdown,
+            SymbolKind::NoEntry,
+            String::from("unsafe_info  🚫  tree_vinespackage_name")
+        ),
         case(
             CrateDetectionStatus::UnsafeDetected,
             OutputFormat::GitHubMarkdown,
@@ -277,69 +418,360 @@ mod handle_text_tree_line_tests {
         assert_eq!(package_text_tree_line, expected_package_text_tree_line);
     }
 
+    #[rstest]
+    fn text_tree_line_package_to_table_line_string_test_proc_macro() {
+        let (krates, metadata) = construct_krates_and_metadata();
+        let cargo_metadata_parameters = CargoMetadataParameters {
+            krates: &krates,
+            metadata: &metadata,
+        };
+        let package_id = metadata.root_package().unwrap().id.clone();
+        let proc_macro_package_id = metadata
+            .packages
+            .iter()
+            .find(|package| package.name == "serde_derive")
+            .unwrap()
+            .id
+            .clone();
+
+        let rs_file_metrics_wrapper = |path: &str, functions: Count| {
+            let mut rs_file_metrics_wrapper = RsFileMetricsWrapper::default();
+            rs_file_metrics_wrapper.metrics.counters.functions = functions;
+            (PathBuf::from(path), rs_file_metrics_wrapper)
+        };
+        let geiger_context = GeigerContext {
+            package_id_to_metrics: vec![
+                (
+                    package_id.clone(),
+                    "package.rs",
+                    Count {
+                        safe: 1,
+                        unsafe_: 0,
+                    },
+                ),
+                (
+                    proc_macro_package_id.clone(),
+                    "proc_macro.rs",
+                    Count {
+                        safe: 0,
+                        unsafe_: 1,
+                    },
+                ),
+            ]
+            .into_iter()
+            .map(|(package_id, path, functions)| {
+                let package_metrics = PackageMetrics {
+                    rs_path_to_metrics: HashMap::from([
+                        rs_file_metrics_wrapper(path, functions),
+                    ]),
+                    ..Default::default()
+                };
+                (package_id, package_metrics)
+            })
+            .collect(),
+        };
+        let rs_files_used =
+            vec![PathBuf::from("package.rs"), PathBuf::from("proc_macro.rs")]
+                .into_iter()
+                .collect();
+        let print_config = PrintConfig {
+            output_format: OutputFormat::Ascii,
+            ..Default::default()
+        };
+        let table_parameters = TableParameters {
+            geiger_context: &geiger_context,
+            print_config: &print_config,
+            rs_files_used: &rs_files_used,
+        };
+        let emoji_symbols = EmojiSymbols::new(OutputFormat::Ascii);
+        let mut total_package_counts = TotalPackageCounts {
+            none_detected_forbids_unsafe: 0,
+            none_detected_denies_unsafe: 0,
+            none_detected_allows_unsafe: 0,
+            unsafe_detected: 0,
+            total_counter_block: Default::default(),
+            total_unused_counter_block: Default::default(),
+            total_safety_comments: Default::default(),
+        };
+        let mut visited_package_ids = HashSet::new();
+        let mut warning_count = 0;
+        let mut handle_package_parameters = HandlePackageParameters {
+            total_package_counts: &mut total_package_counts,
+            visited_package_ids: &mut visited_package_ids,
+            warning_count: &mut warning_count,
+        };
+
+        let lines = vec![package_id, proc_macro_package_id]
+            .into_iter()
+            .map(|package_id| {
+                text_tree_line_package_to_table_line_string(
+                    &cargo_metadata_parameters,
+                    &emoji_symbols,
+                    &mut handle_package_parameters,
+                    package_id,
+                    &table_parameters,
+                    String::new(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert!(!lines[0][0].contains("[proc-macro]"));
+        assert!(lines[1][0].contains("serde_derive"));
+        assert!(lines[1][0].ends_with(" [proc-macro]"));
+        assert_eq!(
+            total_package_counts.total_counter_block.functions,
+            Count {
+                safe: 1,
+                unsafe_: 0
+            }
+        );
+        assert_eq!(total_package_counts.unsafe_detected, 0);
+        assert_eq!(total_package_counts.none_detected_allows_unsafe, 1);
+        assert_eq!(
+            total_package_counts.get_total_detection_status(),
+            CrateDetectionStatus::NoneDetectedAllowsUnsafe
+        );
+    }
+
     #[rstest(
-        input_crate_forbids_unsafe,
+        input_label,
+        input_counter_block,
+        expected_counter_block_text_tree_line,
+        case(
+            "build script",
+            CounterBlock {
+                functions: Count { safe: 1, unsafe_: 0 },
+                ..Default::default()
+            },
+            String::from("0/0        0/0          0/0    0/0     0/0      ❓  tree_vinespackage_name [build script]")
+        ),
+        case(
+            "build script",
+            CounterBlock {
+                functions: Count { safe: 0, unsafe_: 1 },
+                ..Default::default()
+            },
+            String::from("1/1        0/0          0/0    0/0     0/0      ☢\u{fe0f}  tree_vinespackage_name [build script]")
+        ),
+        case(
+            "generated",
+            CounterBlock {
+                functions: Count { safe: 0, unsafe_: 1 },
+                ..Default::default()
+            },
+            String::from("1/1        0/0          0/0    0/0     0/0      ☢\u{fe0f}  tree_vinespackage_name [generated]")
+        ),
+        case(
+            "generated",
+            CounterBlock {
+                unsafe_in_macros: 1,
+                ..Default::default()
+            },
+            String::from("0/0        0/0          0/0    0/0     0/0      ❓  tree_vinespackage_name [generated]")
+        )
+    )]
+    fn construct_counter_block_text_tree_line_test(
+        input_label: &str,
+        input_counter_block: CounterBlock,
+        expected_counter_block_text_tree_line: String,
+    ) {
+        let emoji_symbols = EmojiSymbols::new(OutputFormat::GitHubMarkdown);
+        let counter_block = input_counter_block;
+        let table_parameters = TableParameters {
+            geiger_context: &Default::default(),
+            print_config: &PrintConfig {
+                output_format: OutputFormat::GitHubMarkdown,
+                ..Default::default()
+            },
+            rs_files_used: &Default::default(),
+        };
+
+        let counter_block_text_tree_line =
+            construct_counter_block_text_tree_line(
+                &counter_block,
+                &emoji_symbols,
+                input_label,
+                "package_name",
+                &table_parameters,
+                String::from("tree_vines"),
+            );
+
+        assert_eq!(
+            counter_block_text_tree_line,
+            expected_counter_block_text_tree_line
+        );
+    }
+
+    #[rstest(
+        input_used,
+        input_generated,
+        expected_package_has_unsafe,
+        case(CounterBlock::default(), None, false),
+        case(CounterBlock::default(), Some(CounterBlock::default()), false),
+        case(
+            CounterBlock {
+                functions: Count { safe: 0, unsafe_: 1 },
+                ..Default::default()
+            },
+            None,
+            true
+        ),
+        case(
+            CounterBlock::default(),
+            Some(CounterBlock {
+                functions: Count { safe: 0, unsafe_: 1 },
+                ..Default::default()
+            }),
+            true
+        )
+    )]
+    fn package_has_unsafe_test(
+        input_used: CounterBlock,
+        input_generated: Option<CounterBlock>,
+        expected_package_has_unsafe: bool,
+    ) {
+        let unsafe_info = UnsafeInfo {
+            used: input_used,
+            generated: input_generated,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            package_has_unsafe(&unsafe_info),
+            expected_package_has_unsafe
+        );
+    }
+
+    #[rstest(
+        input_crate_lint_status,
         input_total_inc,
         input_unsafe_found,
         expected_crate_detection_status,
         expected_none_detected_forbids_unsafe,
+        expected_none_detected_denies_unsafe,
         expected_none_detected_allows_unsafe,
         expected_unsafe_detected,
         case(
-            true,
+            LintStatus::Forbid,
             1,
             false,
             CrateDetectionStatus::NoneDetectedForbidsUnsafe,
             1,
             0,
+            0,
             0
         ),
         case(
-            true,
+            LintStatus::Forbid,
             0,
             false,
             CrateDetectionStatus::NoneDetectedForbidsUnsafe,
             0,
             0,
+            0,
             0
         ),
         case(
+            LintStatus::Deny,
+            1,
             false,
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe,
+            0,
+            1,
+            0,
+            0
+        ),
+        case(
+            LintStatus::ConditionalDeny,
+            1,
+            false,
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe,
+            0,
+            1,
+            0,
+            0
+        ),
+        case(
+            LintStatus::ConditionalForbid,
+            1,
+            false,
+            CrateDetectionStatus::NoneDetectedDeniesUnsafe,
+            0,
+            1,
+            0,
+            0
+        ),
+        case(
+            LintStatus::DenyWithLocalAllow,
             1,
             false,
             CrateDetectionStatus::NoneDetectedAllowsUnsafe,
             0,
+            0,
             1,
             0
         ),
         case(
+            LintStatus::Allow,
+            1,
             false,
+            CrateDetectionStatus::NoneDetectedAllowsUnsafe,
+            0,
+            0,
+            1,
+            0
+        ),
+        case(
+            LintStatus::Allow,
             0,
             false,
             CrateDetectionStatus::NoneDetectedAllowsUnsafe,
             0,
             0,
+            0,
             0
         ),
-        case(false, 1, true, CrateDetectionStatus::UnsafeDetected, 0, 0, 1),
-        case(false, 0, true, CrateDetectionStatus::UnsafeDetected, 0, 0, 0)
+        case(
+            LintStatus::Allow,
+            1,
+            true,
+            CrateDetectionStatus::UnsafeDetected,
+            0,
+            0,
+            0,
+            1
+        ),
+        case(
+            LintStatus::Allow,
+            0,
+            true,
+            CrateDetectionStatus::UnsafeDetected,
+            0,
+            0,
+            0,
+            0
+        )
     )]
     fn get_crate_detection_status_and_update_package_counts_test(
-        input_crate_forbids_unsafe: bool,
+        input_crate_lint_status: LintStatus,
         input_total_inc: i32,
         input_unsafe_found: bool,
         expected_crate_detection_status: CrateDetectionStatus,
         expected_none_detected_forbids_unsafe: i32,
+        expected_none_detected_denies_unsafe: i32,
         expected_none_detected_allows_unsafe: i32,
         expected_unsafe_detected: i32,
     ) {
         let mut handle_package_parameters = HandlePackageParameters {
             total_package_counts: &mut TotalPackageCounts {
                 none_detected_forbids_unsafe: 0,
+                none_detected_denies_unsafe: 0,
                 none_detected_allows_unsafe: 0,
                 unsafe_detected: 0,
                 total_counter_block: Default::default(),
                 total_unused_counter_block: Default::default(),
+                total_safety_comments: Default::default(),
             },
             visited_package_ids: &mut Default::default(),
             warning_count: &mut 0,
@@ -347,7 +779,7 @@ mod handle_text_tree_line_tests {
 
         let crate_detection_status =
             get_crate_detection_status_and_update_package_counts(
-                input_crate_forbids_unsafe,
+                input_crate_lint_status,
                 &mut handle_package_parameters,
                 input_total_inc,
                 input_unsafe_found,
@@ -362,6 +794,13 @@ mod handle_text_tree_line_tests {
             expected_none_detected_forbids_unsafe
         );
 
+        assert_eq!(
+            handle_package_parameters
+                .total_package_counts
+                .none_detected_denies_unsafe,
+            expected_none_detected_denies_unsafe
+        );
+
         assert_eq!(
             handle_package_parameters
                 .total_package_counts
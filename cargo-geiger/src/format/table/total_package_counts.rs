@@ -1,36 +1,44 @@
 use crate::format::CrateDetectionStatus;
 
-use cargo_geiger_serde::CounterBlock;
+use cargo_geiger_serde::{CounterBlock, SafetyCommentCoverage};
 
 pub struct TotalPackageCounts {
     pub none_detected_forbids_unsafe: i32,
+    pub none_detected_denies_unsafe: i32,
     pub none_detected_allows_unsafe: i32,
     pub unsafe_detected: i32,
     pub total_counter_block: CounterBlock,
     pub total_unused_counter_block: CounterBlock,
+    pub total_safety_comments: SafetyCommentCoverage,
 }
 
 impl TotalPackageCounts {
     pub fn new() -> TotalPackageCounts {
         TotalPackageCounts {
             none_detected_forbids_unsafe: 0,
+            none_detected_denies_unsafe: 0,
             none_detected_allows_unsafe: 0,
             unsafe_detected: 0,
             total_counter_block: CounterBlock::default(),
             total_unused_counter_block: CounterBlock::default(),
+            total_safety_comments: SafetyCommentCoverage::default(),
         }
     }
 
     pub fn get_total_detection_status(&self) -> CrateDetectionStatus {
         match (
             self.none_detected_forbids_unsafe > 0,
+            self.none_detected_denies_unsafe > 0,
             self.none_detected_allows_unsafe > 0,
             self.unsafe_detected > 0,
         ) {
-            (_, _, true) => CrateDetectionStatus::UnsafeDetected,
-            (true, false, false) => {
+            (_, _, _, true) => CrateDetectionStatus::UnsafeDetected,
+            (true, false, false, false) => {
                 CrateDetectionStatus::NoneDetectedForbidsUnsafe
             }
+            (_, true, false, false) => {
+                CrateDetectionStatus::NoneDetectedDeniesUnsafe
+            }
             _ => CrateDetectionStatus::NoneDetectedAllowsUnsafe,
         }
     }
@@ -20,6 +20,9 @@ use std::path::PathBuf;
 pub struct Graph {
     pub graph: petgraph::Graph<PackageId, DependencyKind>,
     pub nodes: HashMap<PackageId, NodeIndex>,
+    /// Active conditional compilation flags of the target, `None` when all
+    /// targets are scanned or the flags could not be looked up
+    pub cfgs: Option<Vec<Cfg>>,
 }
 
 // Almost unmodified compared to the original in cargo-tree, should be fairly
@@ -42,6 +45,7 @@ pub fn build_graph<'a>(
     let mut graph = Graph {
         graph: petgraph::Graph::new(),
         nodes: HashMap::new(),
+        cfgs: None,
     };
     graph.nodes.insert(
         root_package_id.clone(),
@@ -68,6 +72,10 @@ pub fn build_graph<'a>(
         );
     }
 
+    if graph_configuration.target.is_some() {
+        graph.cfgs = cfgs;
+    }
+
     Ok(graph)
 }
 
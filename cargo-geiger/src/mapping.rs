@@ -7,7 +7,7 @@ use metadata::package_id::ToCargoMetadataPackage;
 use ::krates::Krates;
 use cargo::core::dependency::DepKind;
 use cargo_metadata::Metadata;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::path::PathBuf;
 
@@ -20,6 +20,8 @@ use crate::mapping::metadata::dependency::GetDependencyInformation;
 use crate::mapping::metadata::package::GetPackageInformation;
 use crate::mapping::metadata::GetMetadataPackages;
 
+use ::geiger::CfgSet as GeigerCfgSet;
+
 use cargo_geiger_serde::DependencyKind as CargoGeigerSerdeDependencyKind;
 use cargo_geiger_serde::PackageId as CargoGeigerSerdePackageId;
 use cargo_geiger_serde::Source as CargoGeigerSerdeSource;
@@ -56,6 +58,16 @@ pub trait GetPackageIdInformation {
     ) -> Option<String>;
 }
 
+pub trait GetResolvedFeatures {
+    fn get_resolved_features(
+        &self,
+    ) -> HashMap<&CargoMetadataPackageId, &[String]>;
+}
+
+pub trait IsProcMacro {
+    fn is_proc_macro(&self, package_id: &CargoMetadataPackageId) -> bool;
+}
+
 pub trait GetPackageRoot: GetPackageInformation {
     fn get_root(&self) -> Option<PathBuf> {
         match self.get_package_parent() {
@@ -111,6 +123,10 @@ pub trait ToCargoGeigerSource {
     ) -> CargoGeigerSerdeSource;
 }
 
+pub trait ToGeigerCfgSet {
+    fn to_geiger_cfg_set(&self) -> GeigerCfgSet;
+}
+
 pub trait ToCargoMetadataPackageId: GetDependencyInformation {
     fn to_cargo_metadata_package_id<T: GetMetadataPackages>(
         &self,
@@ -1,11 +1,13 @@
 use super::metadata::package_id::{GetPackageIdRepr, ToCargoMetadataPackage};
-use super::ToCargoGeigerSource;
+use super::{ToCargoGeigerSource, ToGeigerCfgSet};
 
 use cargo_metadata::Metadata;
+use cargo_platform::Cfg;
 use url::Url;
 
 use cargo_geiger_serde::Source as CargoGeigerSerdeSource;
 use cargo_metadata::PackageId as CargoMetadataPackageId;
+use geiger::CfgSet as GeigerCfgSet;
 
 impl ToCargoGeigerSource for CargoMetadataPackageId {
     fn to_cargo_geiger_source(
@@ -21,6 +23,15 @@ impl ToCargoGeigerSource for CargoMetadataPackageId {
     }
 }
 
+impl ToGeigerCfgSet for [Cfg] {
+    fn to_geiger_cfg_set(&self) -> GeigerCfgSet {
+        GeigerCfgSet::from_target_cfgs(self.iter().map(|cfg| match cfg {
+            Cfg::Name(name) => (name.clone(), None),
+            Cfg::KeyPair(key, value) => (key.clone(), Some(value.clone())),
+        }))
+    }
+}
+
 fn handle_source_repr(source_repr: &str) -> CargoGeigerSerdeSource {
     let mut source_repr_vec = source_repr.split('+').collect::<Vec<&str>>();
 
@@ -101,6 +112,7 @@ mod geiger_tests {
     use super::*;
 
     use rstest::*;
+    use std::str::FromStr;
     use url::Url;
 
     #[rstest(
@@ -136,6 +148,22 @@ mod geiger_tests {
         assert_eq!(source, expected_source);
     }
 
+    #[rstest]
+    fn to_geiger_cfg_set_test() {
+        let cfgs = vec![
+            Cfg::from_str("unix").unwrap(),
+            Cfg::from_str("target_os = \"linux\"").unwrap(),
+        ];
+        let cfg_set = cfgs.to_geiger_cfg_set();
+
+        let mut expected_cfg_set = GeigerCfgSet::from_target_cfgs(vec![]);
+        expected_cfg_set.insert(String::from("unix"), None);
+        expected_cfg_set
+            .insert(String::from("target_os"), Some(String::from("linux")));
+
+        assert_eq!(cfg_set, expected_cfg_set);
+    }
+
     #[rstest]
     fn handle_path_source_test() {
         if !cfg!(windows) {
@@ -3,8 +3,8 @@ pub mod package;
 pub mod package_id;
 
 use super::{
-    DepsNotReplaced, GetPackageIdInformation, MatchesIgnoringSource,
-    ToCargoGeigerPackageId, ToCargoMetadataPackageId,
+    DepsNotReplaced, GetPackageIdInformation, GetResolvedFeatures, IsProcMacro,
+    MatchesIgnoringSource, ToCargoGeigerPackageId, ToCargoMetadataPackageId,
 };
 use package_id::ToCargoMetadataPackage;
 
@@ -12,7 +12,7 @@ use crate::mapping::krates::GetNodeForKid;
 use crate::mapping::{ToCargoGeigerDependencyKind, ToCargoGeigerSource};
 
 use cargo_metadata::Metadata;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::slice::Iter;
 
@@ -64,6 +64,34 @@ impl DepsNotReplaced for Metadata {
     }
 }
 
+impl GetResolvedFeatures for Metadata {
+    /// The features enabled for each package by the dependency resolution,
+    /// keyed by package id. Empty when the metadata was collected without
+    /// resolving
+    fn get_resolved_features(
+        &self,
+    ) -> HashMap<&CargoMetadataPackageId, &[String]> {
+        self.resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .map(|node| (&node.id, node.features.as_slice()))
+            .collect()
+    }
+}
+
+impl IsProcMacro for Metadata {
+    /// Whether the package builds a procedural macro, which is compiled for
+    /// and run by the compiler instead of linked into the final artifacts
+    fn is_proc_macro(&self, package_id: &CargoMetadataPackageId) -> bool {
+        self.packages.iter().any(|package| {
+            package.id == *package_id
+                && package.targets.iter().any(|target| {
+                    target.kind.iter().any(|kind| kind == "proc-macro")
+                })
+        })
+    }
+}
+
 impl MatchesIgnoringSource for CargoMetadataDependency {
     fn matches_ignoring_source<
         T: GetNodeForKid,
@@ -210,6 +238,42 @@ mod metadata_tests {
         assert_eq!(cargo_core_package_names, cargo_metadata_package_names);
     }
 
+    #[rstest]
+    fn get_resolved_features_test() {
+        let (_, metadata) = construct_krates_and_metadata();
+        let package = metadata.root_package().unwrap();
+
+        let resolved_features = metadata.get_resolved_features();
+
+        assert_eq!(
+            resolved_features.get(&package.id),
+            Some(&&[String::from("vendored-openssl")][..])
+        );
+        assert_eq!(
+            resolved_features.get(&CargoMetadataPackageId {
+                repr: String::from("not_a_package 0.0.0"),
+            }),
+            None
+        );
+    }
+
+    #[rstest]
+    fn is_proc_macro_test() {
+        let (_, metadata) = construct_krates_and_metadata();
+        let package = metadata.root_package().unwrap();
+        let proc_macro_package = metadata
+            .packages
+            .iter()
+            .find(|package| package.name == "serde_derive")
+            .unwrap();
+
+        assert!(!metadata.is_proc_macro(&package.id));
+        assert!(metadata.is_proc_macro(&proc_macro_package.id));
+        assert!(!metadata.is_proc_macro(&CargoMetadataPackageId {
+            repr: String::from("not_a_package 0.0.0"),
+        }));
+    }
+
     #[rstest]
     fn get_root_test() {
         let (_, metadata) = construct_krates_and_metadata();
@@ -1,6 +1,8 @@
+mod dangerous_apis;
 mod default;
 mod find;
 mod forbid;
+mod manifest_lints;
 mod rs_file;
 
 use crate::args::Args;
@@ -19,13 +21,14 @@ use forbid::scan_forbid_unsafe;
 use cargo::core::Workspace;
 use cargo::{CliError, Config};
 use cargo_geiger_serde::{
-    CounterBlock, DependencyKind, PackageInfo, UnsafeInfo,
+    Count, CounterBlock, DependencyKind, FunctionMetrics, LintStatus,
+    PackageInfo, SafetyCommentCoverage, UnsafeInfo, UnsafeLocation,
 };
 use cargo_metadata::PackageId;
 use krates::NodeId;
 use petgraph::prelude::NodeIndex;
 use petgraph::visit::EdgeRef;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::path::PathBuf;
@@ -53,13 +56,21 @@ pub struct ScanResult {
 #[derive(Default)]
 pub struct GeigerContext {
     pub package_id_to_metrics: HashMap<PackageId, PackageMetrics>,
-    pub ignored_paths: HashSet<PathBuf>,
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct PackageMetrics {
     /// The key is the canonicalized path to the rs source file.
     pub rs_path_to_metrics: HashMap<PathBuf, RsFileMetricsWrapper>,
+    /// Level of the `unsafe_code` lint declared in the `[lints]` table of the
+    /// package manifest.
+    pub manifest_lint_status: LintStatus,
+    /// The `unsafe` usage reported by the `unsafe_code` lint of rustc when
+    /// compiling the package, only populated when requested.
+    pub compiler_lints: Option<CounterBlock>,
+    /// The `unsafe` usage found in the macro expanded source of the package,
+    /// only populated when requested.
+    pub expanded: Option<CounterBlock>,
 }
 
 pub enum ScanMode {
@@ -126,28 +137,189 @@ pub fn unsafe_stats(
         .rs_path_to_metrics
         .iter()
         .filter(|(_, v)| v.is_crate_entry_point)
-        .all(|(_, v)| v.metrics.forbids_unsafe);
+        .all(|(_, v)| v.metrics.forbids_unsafe)
+        || package_metrics.manifest_lint_status == LintStatus::Forbid;
 
     let mut used = CounterBlock::default();
     let mut unused = CounterBlock::default();
+    let mut build_script = None;
+    let mut generated = None;
+    let mut safety_comments = SafetyCommentCoverage::default();
+    let mut plugin_metrics = BTreeMap::new();
 
     for (path_buf, rs_file_metrics_wrapper) in
         &package_metrics.rs_path_to_metrics
     {
+        // Build scripts are compiled and run for every build of the package,
+        // none of their code is counted as unused. Their files shared with
+        // the crate are counted as crate code as well.
+        if rs_file_metrics_wrapper.is_build_script {
+            *build_script.get_or_insert_with(CounterBlock::default) +=
+                rs_file_metrics_wrapper.metrics.counters.clone();
+            if !rs_file_metrics_wrapper.is_shared_with_crate {
+                continue;
+            }
+        }
+        // Code excluded from the target by `#[cfg(..)]` is never used
+        unused += rs_file_metrics_wrapper.metrics.inactive_counters.clone();
+        // Files generated by the build script are reported separately from
+        // the hand written code
+        if rs_file_metrics_wrapper.is_generated {
+            *generated.get_or_insert_with(CounterBlock::default) +=
+                rs_file_metrics_wrapper.metrics.counters.clone();
+            continue;
+        }
         let target = if rs_files_used.contains(path_buf) {
+            safety_comments +=
+                rs_file_metrics_wrapper.metrics.safety_comments.clone();
+            for (name, count) in &rs_file_metrics_wrapper.metrics.plugin_metrics
+            {
+                *plugin_metrics.entry(name.clone()).or_insert(0) += count;
+            }
             &mut used
         } else {
             &mut unused
         };
         *target += rs_file_metrics_wrapper.metrics.counters.clone();
     }
+    // Files generated by the build script are included in the expanded source
+    let macro_expansion_delta =
+        package_metrics.expanded.as_ref().map(|expanded| {
+            macro_expansion_delta(
+                expanded,
+                &(used.clone() + generated.clone().unwrap_or_default()),
+            )
+        });
     UnsafeInfo {
         used,
         unused,
+        build_script,
+        generated,
+        macro_expansion_delta,
+        compiler_lints: package_metrics.compiler_lints.clone(),
         forbids_unsafe,
+        lint_status: crate_lint_status(package_metrics),
+        // Packages without any unsafe block or impl have nothing to document
+        safety_comments: (safety_comments.total > 0).then_some(safety_comments),
+        approximate: package_metrics
+            .rs_path_to_metrics
+            .values()
+            .any(|v| v.metrics.approximate),
+        plugin_metrics,
+        ..Default::default()
     }
 }
 
+/// The unsafe usage only found after macro expansion, the unsafe counts of
+/// the expanded source of a package in excess of the unsafe counts of its
+/// scanned source.
+fn macro_expansion_delta(
+    expanded: &CounterBlock,
+    scanned: &CounterBlock,
+) -> CounterBlock {
+    let delta = |expanded: &Count, scanned: &Count| Count {
+        safe: 0,
+        unsafe_: expanded.unsafe_.saturating_sub(scanned.unsafe_),
+    };
+    CounterBlock {
+        functions: delta(&expanded.functions, &scanned.functions),
+        exprs: delta(&expanded.exprs, &scanned.exprs),
+        item_impls: delta(&expanded.item_impls, &scanned.item_impls),
+        item_traits: delta(&expanded.item_traits, &scanned.item_traits),
+        methods: delta(&expanded.methods, &scanned.methods),
+        ..Default::default()
+    }
+}
+
+/// The level of the `unsafe_code` lint declared by a package. Like the
+/// "forbids unsafe code" metric, the source level is the weakest level
+/// declared by the entry point source files. The manifest `[lints]` table
+/// applies to all of them, so the stronger of the two levels is used. A
+/// package that denies unsafe code but allows it locally in any of its files
+/// is reported as such.
+pub fn crate_lint_status(package_metrics: &PackageMetrics) -> LintStatus {
+    let source_lint_status = package_metrics
+        .rs_path_to_metrics
+        .iter()
+        .filter(|(_, v)| v.is_crate_entry_point)
+        .map(|(_, v)| v.metrics.lint_status)
+        .min()
+        .unwrap_or(LintStatus::Forbid);
+    let lint_status =
+        source_lint_status.max(package_metrics.manifest_lint_status);
+    let allows_unsafe_locally =
+        package_metrics.rs_path_to_metrics.iter().any(|(_, v)| {
+            (!v.is_build_script || v.is_shared_with_crate)
+                && v.metrics.allows_unsafe_locally
+        });
+
+    match lint_status {
+        LintStatus::Deny | LintStatus::ConditionalDeny
+            if allows_unsafe_locally =>
+        {
+            LintStatus::DenyWithLocalAllow
+        }
+        lint_status => lint_status,
+    }
+}
+
+/// The source locations of the `unsafe` usage in the files of a package that
+/// are used by the build, sorted by file and position.
+pub fn unsafe_locations(
+    package_metrics: &PackageMetrics,
+    rs_files_used: &HashSet<PathBuf>,
+) -> Vec<UnsafeLocation> {
+    let mut locations = package_metrics
+        .rs_path_to_metrics
+        .iter()
+        .filter(|(path_buf, _)| rs_files_used.contains(*path_buf))
+        .flat_map(|(_, rs_file_metrics_wrapper)| {
+            rs_file_metrics_wrapper
+                .metrics
+                .unsafe_locations
+                .iter()
+                .cloned()
+        })
+        .collect::<Vec<UnsafeLocation>>();
+    locations.sort();
+    locations
+}
+
+/// The `count` functions with the most unsafe expressions in the used source
+/// files of the given packages, most unsafe first. Functions without unsafe
+/// expressions are never ranked.
+pub fn hotspots<'a, P, I>(
+    packages: I,
+    rs_files_used: &HashSet<PathBuf>,
+    count: usize,
+) -> Vec<(P, FunctionMetrics)>
+where
+    P: Clone,
+    I: IntoIterator<Item = (P, &'a PackageMetrics)>,
+{
+    let mut hotspots = packages
+        .into_iter()
+        .flat_map(|(package_id, package_metrics)| {
+            package_metrics
+                .rs_path_to_metrics
+                .iter()
+                .filter(|(path_buf, _)| rs_files_used.contains(*path_buf))
+                .flat_map(|(_, rs_file_metrics_wrapper)| {
+                    rs_file_metrics_wrapper.metrics.functions.iter()
+                })
+                .filter(|function| function.unsafe_exprs > 0)
+                .map(move |function| (package_id.clone(), function.clone()))
+        })
+        .collect::<Vec<(P, FunctionMetrics)>>();
+    hotspots.sort_by(|(_, a), (_, b)| {
+        b.unsafe_exprs
+            .cmp(&a.unsafe_exprs)
+            .then_with(|| (&a.file, &a.start).cmp(&(&b.file, &b.start)))
+    });
+    hotspots.truncate(count);
+    hotspots
+}
+
 struct ScanDetails {
     rs_files_used: HashSet<PathBuf>,
     geiger_context: GeigerContext,
@@ -185,10 +357,7 @@ fn list_files_used_but_not_scanned(
     rs_files_used
         .iter()
         .cloned()
-        .filter(|p| {
-            !scanned_files.contains(p)
-                && !geiger_context.ignored_paths.contains(p)
-        })
+        .filter(|p| !scanned_files.contains(p))
         .collect()
 }
 
@@ -290,7 +459,9 @@ mod scan_tests {
     use rs_file::RsFileMetricsWrapper;
 
     use crate::lib_tests::construct_krates_and_metadata;
-    use cargo_geiger_serde::{Count, Source, UnsafeInfo};
+    use cargo_geiger_serde::{
+        Count, LineColumn, Source, UnsafeInfo, UnsafeKind,
+    };
     use rstest::*;
     use semver::{BuildMetadata, Prerelease, Version};
     use std::{collections::HashSet, path::PathBuf};
@@ -317,6 +488,7 @@ mod scan_tests {
         let mut graph = Graph {
             graph: Default::default(),
             nodes: Default::default(),
+            cfgs: None,
         };
         graph.graph.add_node(package_id);
 
@@ -394,6 +566,9 @@ mod scan_tests {
                 RsFileMetricsWrapper {
                     metrics: Default::default(),
                     is_crate_entry_point: false,
+                    is_build_script: false,
+                    is_shared_with_crate: false,
+                    is_generated: false,
                 },
             )],
             vec![
@@ -412,17 +587,26 @@ mod scan_tests {
                 RsFileMetricsWrapper {
                     metrics: Default::default(),
                     is_crate_entry_point: false,
+                    is_build_script: false,
+                    is_shared_with_crate: false,
+                    is_generated: false,
                 }),
                 (
                 PathBuf::from("second/file/path.rs"),
                 RsFileMetricsWrapper {
                 metrics: Default::default(),
                 is_crate_entry_point: false,
+                is_build_script: false,
+                is_shared_with_crate: false,
+                is_generated: false,
                 }),
                 (PathBuf::from("third/file/path.rs"),
                 RsFileMetricsWrapper {
                     metrics: Default::default(),
                     is_crate_entry_point: false,
+                    is_build_script: false,
+                    is_shared_with_crate: false,
+                    is_generated: false,
                 }
             )],
             vec![
@@ -448,12 +632,14 @@ mod scan_tests {
         let geiger_context = GeigerContext {
             package_id_to_metrics: vec![(
                 package_id,
-                PackageMetrics { rs_path_to_metrics },
+                PackageMetrics {
+                    rs_path_to_metrics,
+                    ..Default::default()
+                },
             )]
             .iter()
             .cloned()
             .collect(),
-            ignored_paths: HashSet::new(),
         };
 
         let rs_files_used = input_rs_files_used_vec.iter().cloned().collect();
@@ -474,6 +660,7 @@ mod scan_tests {
         let stats = unsafe_stats(&Default::default(), &Default::default());
         let expected = UnsafeInfo {
             forbids_unsafe: true,
+            lint_status: LintStatus::Forbid,
             ..Default::default()
         };
         assert_eq!(stats, expected);
@@ -517,6 +704,105 @@ mod scan_tests {
         assert!(!stats.forbids_unsafe)
     }
 
+    #[rstest(
+        input_entry_point_lint_statuses,
+        input_manifest_lint_status,
+        input_allows_unsafe_locally,
+        expected_lint_status,
+        case(
+            vec![LintStatus::Forbid, LintStatus::Forbid],
+            LintStatus::Allow,
+            false,
+            LintStatus::Forbid
+        ),
+        case(
+            vec![LintStatus::Forbid, LintStatus::ConditionalForbid],
+            LintStatus::Allow,
+            false,
+            LintStatus::ConditionalForbid
+        ),
+        case(
+            vec![LintStatus::Deny, LintStatus::Forbid],
+            LintStatus::Allow,
+            false,
+            LintStatus::Deny
+        ),
+        case(
+            vec![LintStatus::Deny],
+            LintStatus::Allow,
+            true,
+            LintStatus::DenyWithLocalAllow
+        ),
+        case(
+            vec![LintStatus::ConditionalDeny, LintStatus::Forbid],
+            LintStatus::Allow,
+            true,
+            LintStatus::DenyWithLocalAllow
+        ),
+        case(
+            vec![LintStatus::Forbid, LintStatus::Allow],
+            LintStatus::Allow,
+            true,
+            LintStatus::Allow
+        ),
+        case(
+            vec![LintStatus::Allow, LintStatus::Deny],
+            LintStatus::Forbid,
+            false,
+            LintStatus::Forbid
+        ),
+        case(
+            vec![LintStatus::Allow],
+            LintStatus::Deny,
+            true,
+            LintStatus::DenyWithLocalAllow
+        )
+    )]
+    fn crate_lint_status_test(
+        input_entry_point_lint_statuses: Vec<LintStatus>,
+        input_manifest_lint_status: LintStatus,
+        input_allows_unsafe_locally: bool,
+        expected_lint_status: LintStatus,
+    ) {
+        let mut metrics = metrics_from_iter(
+            input_entry_point_lint_statuses.into_iter().enumerate().map(
+                |(index, lint_status)| {
+                    (
+                        format!("entry_point_{}.rs", index),
+                        MetricsBuilder::default()
+                            .lint_status(lint_status)
+                            .set_is_crate_entry_point(true)
+                            .build(),
+                    )
+                },
+            ),
+        );
+        metrics.rs_path_to_metrics.insert(
+            PathBuf::from("module.rs"),
+            MetricsBuilder::default()
+                .allows_unsafe_locally(input_allows_unsafe_locally)
+                .build(),
+        );
+        metrics.manifest_lint_status = input_manifest_lint_status;
+
+        assert_eq!(crate_lint_status(&metrics), expected_lint_status);
+    }
+
+    #[rstest]
+    fn unsafe_stats_report_forbid_unsafe_as_true_if_manifest_forbids_unsafe() {
+        let mut metrics = metrics_from_iter(vec![(
+            "foo.rs",
+            MetricsBuilder::default()
+                .forbids_unsafe(false)
+                .set_is_crate_entry_point(true)
+                .build(),
+        )]);
+        metrics.manifest_lint_status = LintStatus::Forbid;
+        let stats = unsafe_stats(&metrics, &set_of_paths(&["foo.rs"]));
+        assert!(stats.forbids_unsafe);
+        assert_eq!(stats.lint_status, LintStatus::Forbid);
+    }
+
     #[rstest]
     fn unsafe_stats_accumulate_counters() {
         let metrics = metrics_from_iter(vec![
@@ -539,6 +825,364 @@ mod scan_tests {
         assert_eq!(stats.unused.functions.unsafe_, 110);
     }
 
+    #[rstest]
+    fn unsafe_stats_count_build_scripts_separately() {
+        let metrics = metrics_from_iter(vec![
+            ("lib.rs", MetricsBuilder::default().functions(2, 1).build()),
+            (
+                "build.rs",
+                MetricsBuilder::default()
+                    .functions(5, 3)
+                    .inactive_functions(4, 4)
+                    .set_is_build_script(true)
+                    .build(),
+            ),
+            (
+                "build/helper.rs",
+                MetricsBuilder::default()
+                    .functions(20, 10)
+                    .set_is_build_script(true)
+                    .build(),
+            ),
+            (
+                "shared.rs",
+                MetricsBuilder::default()
+                    .functions(100, 50)
+                    .set_is_build_script(true)
+                    .set_is_shared_with_crate(true)
+                    .build(),
+            ),
+        ]);
+        let stats = unsafe_stats(
+            &metrics,
+            &set_of_paths(&["lib.rs", "build.rs", "shared.rs"]),
+        );
+        assert_eq!(
+            stats.used.functions,
+            Count {
+                safe: 102,
+                unsafe_: 51
+            }
+        );
+        assert_eq!(stats.unused, CounterBlock::default());
+        assert_eq!(
+            stats.build_script.unwrap().functions,
+            Count {
+                safe: 125,
+                unsafe_: 63
+            }
+        );
+    }
+
+    #[rstest]
+    fn unsafe_stats_count_generated_files_separately() {
+        let metrics = metrics_from_iter(vec![
+            ("lib.rs", MetricsBuilder::default().functions(2, 1).build()),
+            (
+                "out/bindings.rs",
+                MetricsBuilder::default()
+                    .functions(30, 30)
+                    .inactive_functions(4, 4)
+                    .set_is_generated(true)
+                    .build(),
+            ),
+        ]);
+        let stats = unsafe_stats(
+            &metrics,
+            &set_of_paths(&["lib.rs", "out/bindings.rs"]),
+        );
+        assert_eq!(
+            stats.used.functions,
+            Count {
+                safe: 2,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            stats.unused.functions,
+            Count {
+                safe: 4,
+                unsafe_: 4
+            }
+        );
+        assert_eq!(
+            stats.generated.unwrap().functions,
+            Count {
+                safe: 30,
+                unsafe_: 30
+            }
+        );
+    }
+
+    #[rstest]
+    fn unsafe_stats_report_the_macro_expansion_delta() {
+        let mut metrics = metrics_from_iter(vec![
+            ("lib.rs", MetricsBuilder::default().functions(2, 1).build()),
+            (
+                "out/bindings.rs",
+                MetricsBuilder::default()
+                    .functions(1, 1)
+                    .set_is_generated(true)
+                    .build(),
+            ),
+        ]);
+        metrics.expanded = Some(CounterBlock {
+            functions: Count {
+                safe: 6,
+                unsafe_: 5,
+            },
+            ..Default::default()
+        });
+        let stats = unsafe_stats(
+            &metrics,
+            &set_of_paths(&["lib.rs", "out/bindings.rs"]),
+        );
+        assert_eq!(
+            stats.macro_expansion_delta,
+            Some(CounterBlock {
+                functions: Count {
+                    safe: 0,
+                    unsafe_: 3
+                },
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            unsafe_stats(&PackageMetrics::default(), &HashSet::new())
+                .macro_expansion_delta,
+            None
+        );
+    }
+
+    #[rstest]
+    fn unsafe_stats_without_build_script() {
+        let metrics = metrics_from_iter(vec![(
+            "lib.rs",
+            MetricsBuilder::default().functions(2, 1).build(),
+        )]);
+        let stats = unsafe_stats(&metrics, &set_of_paths(&["lib.rs"]));
+        assert_eq!(stats.build_script, None);
+    }
+
+    #[rstest]
+    fn unsafe_stats_count_inactive_code_as_unused() {
+        let metrics = metrics_from_iter(vec![
+            (
+                "foo.rs",
+                MetricsBuilder::default()
+                    .functions(2, 1)
+                    .inactive_functions(4, 3)
+                    .build(),
+            ),
+            (
+                "bar.rs",
+                MetricsBuilder::default()
+                    .functions(20, 10)
+                    .inactive_functions(40, 30)
+                    .build(),
+            ),
+        ]);
+        let stats = unsafe_stats(&metrics, &set_of_paths(&["foo.rs"]));
+        assert_eq!(stats.used.functions.safe, 2);
+        assert_eq!(stats.used.functions.unsafe_, 1);
+        assert_eq!(stats.unused.functions.safe, 64);
+        assert_eq!(stats.unused.functions.unsafe_, 43);
+    }
+
+    #[rstest]
+    fn unsafe_stats_accumulate_safety_comments_of_used_files() {
+        let metrics = metrics_from_iter(vec![
+            (
+                "foo.rs",
+                MetricsBuilder::default().safety_comments(1, 2).build(),
+            ),
+            (
+                "bar.rs",
+                MetricsBuilder::default().safety_comments(3, 3).build(),
+            ),
+            (
+                "baz.rs",
+                MetricsBuilder::default().safety_comments(0, 5).build(),
+            ),
+        ]);
+        let stats =
+            unsafe_stats(&metrics, &set_of_paths(&["foo.rs", "bar.rs"]));
+        assert_eq!(
+            stats.safety_comments,
+            Some(SafetyCommentCoverage {
+                documented: 4,
+                total: 5
+            })
+        );
+        let stats = unsafe_stats(&metrics, &set_of_paths(&["quux.rs"]));
+        assert_eq!(stats.safety_comments, None);
+    }
+
+    #[rstest]
+    fn unsafe_stats_report_approximate_if_one_file_is_approximate() {
+        let metrics = metrics_from_iter(vec![
+            ("foo.rs", MetricsBuilder::default().build()),
+            (
+                "bar.rs",
+                MetricsBuilder::default().approximate(true).build(),
+            ),
+        ]);
+        let stats = unsafe_stats(&metrics, &set_of_paths(&["foo.rs"]));
+        assert!(stats.approximate);
+        let metrics = metrics_from_iter(vec![(
+            "foo.rs",
+            MetricsBuilder::default().build(),
+        )]);
+        let stats = unsafe_stats(&metrics, &set_of_paths(&["foo.rs"]));
+        assert!(!stats.approximate);
+    }
+
+    #[rstest]
+    fn unsafe_stats_accumulate_plugin_metrics_of_used_files() {
+        let metrics = metrics_from_iter(vec![
+            (
+                "foo.rs",
+                MetricsBuilder::default()
+                    .plugin_metric("unsafe_assert", 2)
+                    .build(),
+            ),
+            (
+                "bar.rs",
+                MetricsBuilder::default()
+                    .plugin_metric("unsafe_assert", 1)
+                    .plugin_metric("banned_imports", 1)
+                    .build(),
+            ),
+            (
+                "quux.rs",
+                MetricsBuilder::default()
+                    .plugin_metric("unsafe_assert", 8)
+                    .build(),
+            ),
+        ]);
+        let stats =
+            unsafe_stats(&metrics, &set_of_paths(&["foo.rs", "bar.rs"]));
+        let expected_plugin_metrics = vec![
+            (String::from("banned_imports"), 1),
+            (String::from("unsafe_assert"), 3),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+        assert_eq!(stats.plugin_metrics, expected_plugin_metrics);
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap()["plugin_metrics"],
+            serde_json::json!({ "banned_imports": 1, "unsafe_assert": 3 })
+        );
+
+        let stats = unsafe_stats(&metrics, &HashSet::new());
+        assert!(serde_json::to_value(&stats)
+            .unwrap()
+            .get("plugin_metrics")
+            .is_none());
+    }
+
+    #[rstest]
+    fn hotspots_rank_functions_of_used_files_by_unsafe_exprs() {
+        let function = |file: &str, line, unsafe_exprs| FunctionMetrics {
+            path: format!("f{}", line),
+            file: PathBuf::from(file),
+            start: LineColumn { line, column: 0 },
+            end: LineColumn {
+                line: line + 2,
+                column: 1,
+            },
+            is_unsafe: false,
+            unsafe_exprs,
+        };
+        let foo_metrics = metrics_from_iter(vec![
+            (
+                "foo.rs",
+                MetricsBuilder::default()
+                    .functions_metrics(vec![
+                        function("foo.rs", 1, 2),
+                        function("foo.rs", 5, 0),
+                        function("foo.rs", 9, 7),
+                    ])
+                    .build(),
+            ),
+            (
+                "quux.rs",
+                MetricsBuilder::default()
+                    .functions_metrics(vec![function("quux.rs", 1, 100)])
+                    .build(),
+            ),
+        ]);
+        let bar_metrics = metrics_from_iter(vec![(
+            "bar.rs",
+            MetricsBuilder::default()
+                .functions_metrics(vec![
+                    function("bar.rs", 1, 2),
+                    function("bar.rs", 5, 3),
+                ])
+                .build(),
+        )]);
+        let packages = vec![("foo", &foo_metrics), ("bar", &bar_metrics)];
+        let rs_files_used = set_of_paths(&["foo.rs", "bar.rs"]);
+
+        let top_hotspots = hotspots(packages.clone(), &rs_files_used, 3);
+        assert_eq!(
+            top_hotspots,
+            vec![
+                ("foo", function("foo.rs", 9, 7)),
+                ("bar", function("bar.rs", 5, 3)),
+                ("bar", function("bar.rs", 1, 2)),
+            ]
+        );
+        let all_hotspots = hotspots(packages, &rs_files_used, 10);
+        assert_eq!(all_hotspots.len(), 4);
+    }
+
+    #[rstest]
+    fn unsafe_locations_only_include_used_files_in_sorted_order() {
+        let location = |file: &str, line| UnsafeLocation {
+            file: PathBuf::from(file),
+            start: LineColumn { line, column: 4 },
+            end: LineColumn {
+                line: line + 2,
+                column: 5,
+            },
+            kind: UnsafeKind::Block,
+        };
+        let metrics = metrics_from_iter(vec![
+            (
+                "foo.rs",
+                MetricsBuilder::default()
+                    .unsafe_locations(vec![
+                        location("foo.rs", 20),
+                        location("foo.rs", 3),
+                    ])
+                    .build(),
+            ),
+            (
+                "bar.rs",
+                MetricsBuilder::default()
+                    .unsafe_locations(vec![location("bar.rs", 10)])
+                    .build(),
+            ),
+            (
+                "baz.rs",
+                MetricsBuilder::default()
+                    .unsafe_locations(vec![location("baz.rs", 1)])
+                    .build(),
+            ),
+        ]);
+        let locations =
+            unsafe_locations(&metrics, &set_of_paths(&["foo.rs", "bar.rs"]));
+        assert_eq!(
+            locations,
+            vec![
+                location("bar.rs", 10),
+                location("foo.rs", 3),
+                location("foo.rs", 20),
+            ]
+        );
+    }
+
     fn metrics_from_iter<I, P>(it: I) -> PackageMetrics
     where
         I: IntoIterator<Item = (P, RsFileMetricsWrapper)>,
@@ -549,6 +1193,7 @@ mod scan_tests {
                 .into_iter()
                 .map(|(p, m)| (p.into(), m))
                 .collect(),
+            ..Default::default()
         }
     }
 
@@ -571,16 +1216,79 @@ mod scan_tests {
             self
         }
 
+        fn lint_status(mut self, lint_status: LintStatus) -> Self {
+            self.inner.metrics.lint_status = lint_status;
+            self
+        }
+
+        fn allows_unsafe_locally(mut self, yes: bool) -> Self {
+            self.inner.metrics.allows_unsafe_locally = yes;
+            self
+        }
+
         fn functions(mut self, safe: u64, unsafe_: u64) -> Self {
             self.inner.metrics.counters.functions = Count { safe, unsafe_ };
             self
         }
 
+        fn inactive_functions(mut self, safe: u64, unsafe_: u64) -> Self {
+            self.inner.metrics.inactive_counters.functions =
+                Count { safe, unsafe_ };
+            self
+        }
+
+        fn approximate(mut self, yes: bool) -> Self {
+            self.inner.metrics.approximate = yes;
+            self
+        }
+
+        fn plugin_metric(mut self, name: &str, count: u64) -> Self {
+            self.inner
+                .metrics
+                .plugin_metrics
+                .insert(name.to_string(), count);
+            self
+        }
+
+        fn safety_comments(mut self, documented: u64, total: u64) -> Self {
+            self.inner.metrics.safety_comments =
+                SafetyCommentCoverage { documented, total };
+            self
+        }
+
+        fn functions_metrics(
+            mut self,
+            functions: Vec<FunctionMetrics>,
+        ) -> Self {
+            self.inner.metrics.functions = functions;
+            self
+        }
+
+        fn unsafe_locations(mut self, locations: Vec<UnsafeLocation>) -> Self {
+            self.inner.metrics.unsafe_locations = locations;
+            self
+        }
+
         fn set_is_crate_entry_point(mut self, yes: bool) -> Self {
             self.inner.is_crate_entry_point = yes;
             self
         }
 
+        fn set_is_shared_with_crate(mut self, yes: bool) -> Self {
+            self.inner.is_shared_with_crate = yes;
+            self
+        }
+
+        fn set_is_build_script(mut self, yes: bool) -> Self {
+            self.inner.is_build_script = yes;
+            self
+        }
+
+        fn set_is_generated(mut self, yes: bool) -> Self {
+            self.inner.is_generated = yes;
+            self
+        }
+
         fn build(self) -> RsFileMetricsWrapper {
             self.inner
         }
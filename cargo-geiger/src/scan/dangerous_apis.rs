@@ -0,0 +1,149 @@
+use geiger::DangerousApiRules;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Name of the configuration file read from the root of the workspace.
+const CONFIG_FILE_NAME: &str = "geiger.toml";
+
+/// The dangerous-API rules for a workspace: the built-in rules of geiger,
+/// adjusted by the `[dangerous-apis]` table of a `geiger.toml` file at the
+/// root of the workspace. A missing file leaves the built-in rules unchanged,
+/// and so does an invalid one, with a warning. Methods are written with the
+/// type of their receiver, such as `Vec.set_len`.
+///
+/// ```toml
+/// [dangerous-apis]
+/// builtin = true
+/// add = ["Rc::from_raw", "ptr::copy_nonoverlapping"]
+/// remove = ["slice.get_unchecked"]
+/// ```
+pub fn dangerous_api_rules(workspace_root: &Path) -> DangerousApiRules {
+    let config_path = workspace_root.join(CONFIG_FILE_NAME);
+    let content = match fs::read_to_string(&config_path) {
+        Ok(content) => content,
+        Err(_) => return DangerousApiRules::builtin(),
+    };
+    let rules = toml::from_str::<Table>(&content)
+        .map_err(|error| error.to_string())
+        .and_then(|config| match config.get("dangerous-apis") {
+            Some(dangerous_apis) => rules_from_config(dangerous_apis),
+            None => Ok(DangerousApiRules::builtin()),
+        });
+    rules.unwrap_or_else(|error| {
+        eprintln!(
+            "WARNING: Failed to read dangerous APIs from {}, using the \
+             built-in rules: {}",
+            config_path.display(),
+            error
+        );
+        DangerousApiRules::builtin()
+    })
+}
+
+fn rules_from_config(
+    dangerous_apis: &Value,
+) -> Result<DangerousApiRules, String> {
+    if !dangerous_apis.is_table() {
+        return Err(String::from("`dangerous-apis` is not a table"));
+    }
+    let builtin = match dangerous_apis.get("builtin") {
+        Some(builtin) => builtin
+            .as_bool()
+            .ok_or_else(|| String::from("`builtin` is not a boolean"))?,
+        None => true,
+    };
+    let mut rules = if builtin {
+        DangerousApiRules::builtin()
+    } else {
+        DangerousApiRules::new()
+    };
+    for rule in string_array(dangerous_apis, "add")? {
+        rules.insert(rule);
+    }
+    for rule in string_array(dangerous_apis, "remove")? {
+        rules.remove(rule);
+    }
+    Ok(rules)
+}
+
+fn string_array<'a>(
+    table: &'a Value,
+    key: &str,
+) -> Result<Vec<&'a str>, String> {
+    let not_a_string_array = || format!("`{}` is not an array of strings", key);
+    match table.get(key) {
+        Some(value) => value
+            .as_array()
+            .ok_or_else(not_a_string_array)?
+            .iter()
+            .map(|rule| rule.as_str().ok_or_else(not_a_string_array))
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod dangerous_apis_tests {
+    use super::*;
+
+    use rstest::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[rstest(
+        input_config,
+        expected_rules,
+        case("[package]\nname = \"foo\"", DangerousApiRules::builtin()),
+        case("[dangerous-apis]", DangerousApiRules::builtin()),
+        case("[dangerous-apis", DangerousApiRules::builtin()),
+        case("dangerous-apis = 1", DangerousApiRules::builtin()),
+        case(
+            "[dangerous-apis]\nbuiltin = false\nadd = \"Rc::from_raw\"",
+            DangerousApiRules::builtin()
+        ),
+        case(
+            "[dangerous-apis]\nbuiltin = false\nremove = [1]",
+            DangerousApiRules::builtin()
+        ),
+        case(
+            "[dangerous-apis]\nbuiltin = false\nadd = [\"Rc::from_raw\"]",
+            {
+                let mut rules = DangerousApiRules::new();
+                rules.insert("Rc::from_raw");
+                rules
+            }
+        ),
+        case(
+            "[dangerous-apis]\nadd = [\"Rc::from_raw\"]\nremove = [\"slice.get_unchecked\"]",
+            {
+                let mut rules = DangerousApiRules::builtin();
+                rules.insert("Rc::from_raw");
+                rules.remove("slice.get_unchecked");
+                rules
+            }
+        )
+    )]
+    fn dangerous_api_rules_test(
+        input_config: &str,
+        expected_rules: DangerousApiRules,
+    ) {
+        let temp_dir = tempdir().unwrap();
+        let mut config =
+            File::create(temp_dir.path().join(CONFIG_FILE_NAME)).unwrap();
+        writeln!(config, "{}", input_config).unwrap();
+
+        assert_eq!(dangerous_api_rules(temp_dir.path()), expected_rules);
+    }
+
+    #[rstest]
+    fn dangerous_api_rules_test_without_config() {
+        let temp_dir = tempdir().unwrap();
+
+        assert_eq!(
+            dangerous_api_rules(temp_dir.path()),
+            DangerousApiRules::builtin()
+        );
+    }
+}
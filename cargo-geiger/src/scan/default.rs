@@ -3,13 +3,19 @@ mod table;
 use crate::args::FeaturesArgs;
 use crate::format::print_config::OutputFormat;
 use crate::graph::Graph;
-use crate::mapping::CargoMetadataParameters;
-use crate::scan::rs_file::resolve_rs_file_deps;
+use crate::mapping::{
+    CargoMetadataParameters, IsProcMacro, ToCargoGeigerPackageId,
+};
+use crate::scan::rs_file::{resolve_rs_file_deps, RsFileDeps};
 
-use super::find::find_unsafe;
+use super::dangerous_apis::dangerous_api_rules;
+use super::find::{
+    find_unsafe, manifest_dir_package_ids, resolve_rs_files_from_module_trees,
+};
 use super::{
-    list_files_used_but_not_scanned, package_metrics, unsafe_stats,
-    ScanDetails, ScanMode, ScanParameters, ScanResult,
+    hotspots, list_files_used_but_not_scanned, package_metrics,
+    unsafe_locations, unsafe_stats, GeigerContext, PackageMetrics, ScanDetails,
+    ScanMode, ScanParameters, ScanResult,
 };
 
 use table::scan_to_table;
@@ -19,8 +25,11 @@ use cargo::core::resolver::features::CliFeatures;
 use cargo::core::Workspace;
 use cargo::ops::CompileOptions;
 use cargo::{CliError, Config};
-use cargo_geiger_serde::{ReportEntry, SafetyReport};
+use cargo_geiger_serde::{CounterBlock, Hotspot, ReportEntry, SafetyReport};
 use cargo_metadata::PackageId;
+use geiger::DangerousApiRules;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
 
 pub fn scan_unsafe(
     cargo_metadata_parameters: &CargoMetadataParameters,
@@ -95,6 +104,7 @@ fn build_compile_options<'a>(
 
 fn scan(
     cargo_metadata_parameters: &CargoMetadataParameters,
+    graph: &Graph,
     scan_parameters: &ScanParameters,
     workspace: &Workspace,
 ) -> Result<ScanDetails, CliError> {
@@ -103,14 +113,89 @@ fn scan(
         scan_parameters.config,
     );
 
-    match resolve_rs_file_deps(&compile_options, workspace) {
-        Ok(rs_files_used) => {
-            let geiger_context = find_unsafe(
+    // Files generated by build scripts are only known from a build
+    let rs_file_deps = if scan_parameters.args.no_build {
+        Ok(RsFileDeps {
+            rs_files_used: resolve_rs_files_from_module_trees(
                 cargo_metadata_parameters,
+                graph.cfgs.as_deref(),
                 scan_parameters.config,
+            )?,
+            ..Default::default()
+        })
+    } else {
+        resolve_rs_file_deps(
+            &compile_options,
+            scan_parameters.args.expand_macros,
+            scan_parameters.args.compiler_lints,
+            workspace,
+        )
+    };
+
+    match rs_file_deps {
+        Ok(RsFileDeps {
+            rs_files_used,
+            generated_rs_files,
+            unsafe_code_lints,
+            expanded,
+            macro_expansion_errors,
+        }) => {
+            let dangerous_api_rules = if scan_parameters.args.dangerous_apis {
+                dangerous_api_rules(
+                    cargo_metadata_parameters
+                        .metadata
+                        .workspace_root
+                        .as_std_path(),
+                )
+            } else {
+                DangerousApiRules::new()
+            };
+            let mut geiger_context = find_unsafe(
+                cargo_metadata_parameters,
+                graph.cfgs.as_deref(),
+                &dangerous_api_rules,
+                scan_parameters.config,
+                &generated_rs_files,
                 ScanMode::Full,
                 scan_parameters.print_config,
             )?;
+            let manifest_dir_package_ids = manifest_dir_package_ids(
+                &cargo_metadata_parameters.metadata.packages,
+            );
+            set_package_counter_blocks(
+                &mut geiger_context,
+                &manifest_dir_package_ids,
+                unsafe_code_lints,
+                |package_metrics, counter_block| {
+                    package_metrics.compiler_lints = Some(counter_block)
+                },
+            );
+            let package_names = cargo_metadata_parameters
+                .metadata
+                .packages
+                .iter()
+                .filter_map(|package| {
+                    let manifest_dir =
+                        package.manifest_path.parent()?.canonicalize().ok()?;
+                    Some((
+                        manifest_dir,
+                        format!("{} {}", package.name, package.version),
+                    ))
+                })
+                .collect();
+            for warning in
+                macro_expansion_warnings(macro_expansion_errors, &package_names)
+            {
+                eprintln!("{}", warning);
+            }
+            set_package_counter_blocks(
+                &mut geiger_context,
+                &manifest_dir_package_ids,
+                expanded,
+                |package_metrics, counter_block| {
+                    package_metrics.expanded = Some(counter_block)
+                },
+            );
             Ok(ScanDetails {
                 rs_files_used,
                 geiger_context,
@@ -120,6 +205,59 @@ fn scan(
     }
 }
 
+/// One warning for each distinct failure to expand macros, naming the
+/// packages, by canonicalized manifest directory, that failed with it. All
+/// the packages usually fail the same way, like when rustc is not a nightly
+/// toolchain.
+fn macro_expansion_warnings(
+    macro_expansion_errors: HashMap<PathBuf, String>,
+    package_names: &HashMap<PathBuf, String>,
+) -> Vec<String> {
+    let mut package_names_by_error = BTreeMap::<String, Vec<String>>::new();
+    for (manifest_dir, error) in macro_expansion_errors {
+        let package_name = package_names
+            .get(&manifest_dir)
+            .cloned()
+            .unwrap_or_else(|| manifest_dir.display().to_string());
+        package_names_by_error
+            .entry(error)
+            .or_default()
+            .push(package_name);
+    }
+    package_names_by_error
+        .into_iter()
+        .map(|(error, mut package_names)| {
+            package_names.sort();
+            format!(
+                "WARNING: Failed to expand the macros of {}, the macro \
+                 expanded source was not scanned: {}",
+                package_names.join(", "),
+                error
+            )
+        })
+        .collect()
+}
+
+/// Store the counters collected during the build for each package, keyed by
+/// the canonicalized manifest directory of the package, in its metrics.
+fn set_package_counter_blocks(
+    geiger_context: &mut GeigerContext,
+    manifest_dir_package_ids: &HashMap<PathBuf, PackageId>,
+    counter_blocks: HashMap<PathBuf, CounterBlock>,
+    set_counter_block: fn(&mut PackageMetrics, CounterBlock),
+) {
+    for (manifest_dir, counter_block) in counter_blocks {
+        if let Some(package_metrics) = manifest_dir_package_ids
+            .get(&manifest_dir)
+            .and_then(|package_id| {
+                geiger_context.package_id_to_metrics.get_mut(package_id)
+            })
+        {
+            set_counter_block(package_metrics, counter_block);
+        }
+    }
+}
+
 fn scan_to_report(
     cargo_metadata_parameters: &CargoMetadataParameters,
     graph: &Graph,
@@ -131,8 +269,20 @@ fn scan_to_report(
     let ScanDetails {
         rs_files_used,
         geiger_context,
-    } = scan(cargo_metadata_parameters, scan_parameters, workspace)?;
+    } = scan(cargo_metadata_parameters, graph, scan_parameters, workspace)?;
     let mut report = SafetyReport::default();
+    let mut scanned_packages = Vec::new();
+    let proc_macro_package_ids = graph
+        .nodes
+        .keys()
+        .filter(|package_id| {
+            cargo_metadata_parameters.metadata.is_proc_macro(package_id)
+        })
+        .filter_map(|package_id| {
+            package_id
+                .to_cargo_geiger_package_id(cargo_metadata_parameters.metadata)
+        })
+        .collect::<HashSet<_>>();
     for (package, package_metrics_option) in package_metrics(
         cargo_metadata_parameters,
         &geiger_context,
@@ -146,12 +296,57 @@ fn scan_to_report(
                 continue;
             }
         };
-        let unsafe_info = unsafe_stats(&package_metrics, &rs_files_used);
+        let mut unsafe_info = unsafe_stats(&package_metrics, &rs_files_used);
+        if scan_parameters.args.unsafe_locations {
+            unsafe_info.locations =
+                unsafe_locations(&package_metrics, &rs_files_used);
+        }
+        // Packages without any classified operation report zero of each kind
+        for counter_block in [&mut unsafe_info.used, &mut unsafe_info.unused] {
+            let unsafe_operations = counter_block.unsafe_operations.take();
+            counter_block.unsafe_operations = scan_parameters
+                .args
+                .unsafe_operations
+                .then(|| unsafe_operations.unwrap_or_default());
+            let unsafe_scopes = counter_block.unsafe_scopes.take();
+            counter_block.unsafe_scopes = scan_parameters
+                .args
+                .unsafe_scopes
+                .then(|| unsafe_scopes.unwrap_or_default());
+        }
+        let safety_comments = unsafe_info.safety_comments.take();
+        unsafe_info.safety_comments = scan_parameters
+            .args
+            .safety_comments
+            .then(|| safety_comments.unwrap_or_default());
+        let package_id = package.id.clone();
         let entry = ReportEntry {
             package,
             unsafety: unsafe_info,
         };
-        report.packages.insert(entry.package.id.clone(), entry);
+        if proc_macro_package_ids.contains(&package_id) {
+            report.proc_macro_packages.insert(package_id.clone(), entry);
+        } else {
+            report.packages.insert(package_id.clone(), entry);
+        }
+        scanned_packages.push((package_id, package_metrics));
+    }
+    if let Some(count) = scan_parameters.args.hotspots {
+        report.hotspots = hotspots(
+            scanned_packages
+                .iter()
+                .filter(|(package_id, _)| {
+                    !proc_macro_package_ids.contains(package_id)
+                })
+                .map(|(package_id, package_metrics)| {
+                    (package_id.clone(), package_metrics)
+                }),
+            &rs_files_used,
+            count,
+        )
+        .into_iter()
+        .map(|(package, function)| Hotspot { package, function })
+        .collect();
     }
     report.used_but_not_scanned_files =
         list_files_used_but_not_scanned(&geiger_context, &rs_files_used)
@@ -212,4 +407,39 @@ mod default_tests {
             args.no_default_features
         );
     }
+
+    #[rstest]
+    fn macro_expansion_warnings_test() {
+        let nightly_error = String::from(
+            "error: the option `Z` is only accepted on the nightly compiler",
+        );
+        let macro_expansion_errors = HashMap::from([
+            (PathBuf::from("/b"), nightly_error.clone()),
+            (PathBuf::from("/a"), nightly_error.clone()),
+            (PathBuf::from("/c"), String::from("error: recursion limit")),
+            (PathBuf::from("/unknown"), nightly_error),
+        ]);
+        let package_names = HashMap::from([
+            (PathBuf::from("/a"), String::from("a 0.1.0")),
+            (PathBuf::from("/b"), String::from("b 0.2.0")),
+            (PathBuf::from("/c"), String::from("c 0.3.0")),
+        ]);
+
+        assert_eq!(
+            macro_expansion_warnings(macro_expansion_errors, &package_names),
+            vec![
+                String::from(
+                    "WARNING: Failed to expand the macros of c 0.3.0, the \
+                     macro expanded source was not scanned: error: \
+                     recursion limit"
+                ),
+                String::from(
+                    "WARNING: Failed to expand the macros of /unknown, a \
+                     0.1.0, b 0.2.0, the macro expanded source was not \
+                     scanned: error: the option `Z` is only accepted on the \
+                     nightly compiler"
+                ),
+            ]
+        );
+    }
 }
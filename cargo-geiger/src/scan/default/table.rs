@@ -2,16 +2,19 @@ use crate::args::Verbosity;
 use crate::format::emoji_symbols::EmojiSymbols;
 use crate::format::print_config::OutputFormat;
 use crate::format::table::{
-    create_table_from_text_tree_lines, TableParameters, UNSAFE_COUNTERS_HEADER,
+    create_table_from_text_tree_lines, TableParameters, EXTRA_COUNTERS_HEADER,
+    SAFETY_COMMENTS_HEADER, UNSAFE_COUNTERS_HEADER,
 };
 use crate::format::SymbolKind;
 use crate::graph::Graph;
-use crate::mapping::CargoMetadataParameters;
+use crate::mapping::{
+    CargoMetadataParameters, GetPackageIdInformation, IsProcMacro,
+};
 use crate::tree::traversal::walk_dependency_tree;
 
 use super::super::{
-    construct_rs_files_used_lines, list_files_used_but_not_scanned,
-    ScanDetails, ScanParameters, ScanResult,
+    construct_rs_files_used_lines, hotspots, list_files_used_but_not_scanned,
+    GeigerContext, ScanDetails, ScanParameters, ScanResult,
 };
 use super::scan;
 
@@ -19,6 +22,8 @@ use cargo::core::Workspace;
 use cargo::CliError;
 use cargo_metadata::PackageId;
 use colored::Colorize;
+use std::collections::HashSet;
+use std::path::PathBuf;
 
 pub fn scan_to_table(
     cargo_metadata_parameters: &CargoMetadataParameters,
@@ -32,7 +37,7 @@ pub fn scan_to_table(
     let ScanDetails {
         rs_files_used,
         geiger_context,
-    } = scan(cargo_metadata_parameters, scan_parameters, workspace)?;
+    } = scan(cargo_metadata_parameters, graph, scan_parameters, workspace)?;
 
     if scan_parameters.args.verbosity != Verbosity::Quiet {
         let mut rs_files_used_lines =
@@ -45,6 +50,7 @@ pub fn scan_to_table(
     let mut output_key_lines = construct_key_lines(
         &emoji_symbols,
         scan_parameters.print_config.output_format,
+        scan_parameters.print_config.extra_columns,
     );
     combined_scan_output_lines.append(&mut output_key_lines);
 
@@ -70,6 +76,16 @@ pub fn scan_to_table(
     );
     combined_scan_output_lines.append(&mut scan_output_lines);
 
+    if let Some(count) = scan_parameters.args.hotspots {
+        let mut hotspot_lines = construct_hotspot_lines(
+            cargo_metadata_parameters,
+            &geiger_context,
+            &rs_files_used,
+            count,
+        );
+        combined_scan_output_lines.append(&mut hotspot_lines);
+    }
+
     let used_but_not_scanned =
         list_files_used_but_not_scanned(&geiger_context, &rs_files_used);
     warning_count += used_but_not_scanned.len() as u64;
@@ -86,9 +102,50 @@ pub fn scan_to_table(
     })
 }
 
+/// One line per function with the most unsafe expressions, with its count,
+/// package, source location and path.
+fn construct_hotspot_lines(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    geiger_context: &GeigerContext,
+    rs_files_used: &HashSet<PathBuf>,
+    count: usize,
+) -> Vec<String> {
+    let mut hotspot_lines =
+        vec![String::new(), String::from("Unsafe hotspots:")];
+    // Procedural macros are reported apart from the packages they are used
+    // by, like in the totals they are left out of the ranking
+    let packages =
+        geiger_context
+            .package_id_to_metrics
+            .iter()
+            .filter(|(id, _)| {
+                !cargo_metadata_parameters.metadata.is_proc_macro(id)
+            });
+    for (package_id, function) in hotspots(packages, rs_files_used, count) {
+        let package = match package_id
+            .get_package_id_name_and_version(cargo_metadata_parameters.krates)
+        {
+            Some((name, version)) => format!("{} {}", name, version),
+            None => package_id.to_string(),
+        };
+        hotspot_lines.push(format!(
+            "{: >6}  {}{}  {}  {}:{}",
+            function.unsafe_exprs,
+            function.path,
+            if function.is_unsafe { " (unsafe)" } else { "" },
+            package,
+            function.file.display(),
+            function.start.line
+        ));
+    }
+    hotspot_lines.push(String::new());
+    hotspot_lines
+}
+
 fn construct_key_lines(
     emoji_symbols: &EmojiSymbols,
     output_format: OutputFormat,
+    show_extra_columns: bool,
 ) -> Vec<String> {
     let mut output_key_lines = vec![String::new()];
 
@@ -117,6 +174,8 @@ fn construct_key_lines(
     output_key_lines.push(String::from("Symbols: "));
 
     let forbids = "No `unsafe` usage found, declares #![forbid(unsafe_code)]";
+    let denies = "No `unsafe` usage found, declares #![deny(unsafe_code)] or \
+                  #![forbid(unsafe_code)] in a cfg_attr";
     let unknown = "No `unsafe` usage found, missing #![forbid(unsafe_code)]";
     let guilty = "`unsafe` usage found";
 
@@ -132,6 +191,7 @@ fn construct_key_lines(
 
     let symbol_kinds_to_string_values = vec![
         (SymbolKind::Lock, "", forbids),
+        (SymbolKind::NoEntry, "", denies),
         (SymbolKind::QuestionMark, "", unknown),
         (SymbolKind::Rads, shift_sequence, guilty),
     ];
@@ -149,11 +209,15 @@ fn construct_key_lines(
 
     output_key_lines.push(String::new());
 
-    let key = UNSAFE_COUNTERS_HEADER
-        .iter()
-        .map(|s| s.to_owned())
-        .collect::<Vec<_>>()
-        .join(" ");
+    let (dependency_header, counters_headers) =
+        UNSAFE_COUNTERS_HEADER.split_last().unwrap();
+    let mut headers = counters_headers.to_vec();
+    if show_extra_columns {
+        headers.extend_from_slice(&EXTRA_COUNTERS_HEADER);
+        headers.push(SAFETY_COMMENTS_HEADER);
+    }
+    headers.push(*dependency_header);
+    let key = headers.join(" ");
 
     match output_format {
         OutputFormat::GitHubMarkdown => output_key_lines.push(key),
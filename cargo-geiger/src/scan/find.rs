@@ -1,17 +1,25 @@
 use crate::format::print_config::PrintConfig;
-use crate::mapping::{CargoMetadataParameters, GetPackageRoot};
+use crate::mapping::{
+    CargoMetadataParameters, GetPackageRoot, GetResolvedFeatures,
+    ToGeigerCfgSet,
+};
 use crate::scan::rs_file::{
     into_is_entry_point_and_path_buf, into_rs_code_file, into_target_kind,
     is_file_with_ext, RsFile, RsFileMetricsWrapper,
 };
 use crate::scan::PackageMetrics;
 
+use super::manifest_lints::manifest_lint_status;
 use super::{GeigerContext, ScanMode};
 
+use cargo::core::manifest::TargetKind;
 use cargo::{CargoResult, CliError, Config};
 use cargo_metadata::PackageId;
+use cargo_platform::Cfg;
 use geiger::find::find_unsafe_in_file;
-use geiger::{IncludeTests, RsFileMetrics, ScanFileError};
+use geiger::{
+    resolve_module_tree, CfgSet, DangerousApiRules, IncludeTests, ScanFileError,
+};
 use rayon::{in_place_scope, prelude::*};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
@@ -21,18 +29,34 @@ use std::sync::mpsc::sync_channel;
 use std::sync::{Arc, Mutex};
 use walkdir::WalkDir;
 
+/// Scan the source files of all packages, and the `generated_rs_files` their
+/// build scripts generated, keyed by path and mapped to the manifest
+/// directory of their package. Code excluded by `#[cfg(..)]` attributes that
+/// do not hold for `cfgs` is counted as inactive, when the target cfgs are
+/// known. Calls to the APIs of `dangerous_api_rules` are counted by rule.
 pub fn find_unsafe(
     cargo_metadata_parameters: &CargoMetadataParameters,
+    cfgs: Option<&[Cfg]>,
+    dangerous_api_rules: &DangerousApiRules,
     config: &Config,
+    generated_rs_files: &HashMap<PathBuf, PathBuf>,
     mode: ScanMode,
     print_config: &PrintConfig,
 ) -> Result<GeigerContext, CliError> {
+    let cfg_set = cfgs
+        .map(ToGeigerCfgSet::to_geiger_cfg_set)
+        .unwrap_or_default();
     let mut progress = cargo::util::Progress::new("Scanning", config);
     let geiger_context = find_unsafe_in_packages_with_progress(
-        print_config.allow_partial_results,
-        cargo_metadata_parameters,
-        print_config.include_tests,
-        mode,
+        FindParameters {
+            allow_partial_results: print_config.allow_partial_results,
+            cargo_metadata_parameters,
+            cfg_set: &cfg_set,
+            dangerous_api_rules,
+            generated_rs_files,
+            include_tests: print_config.include_tests,
+            mode,
+        },
         |progress_count, count| {
             progress.tick(progress_count, count, "find_unsafe_tick")
         },
@@ -42,11 +66,70 @@ pub fn find_unsafe(
     Ok(geiger_context)
 }
 
-fn find_unsafe_in_packages_with_progress<F>(
-    allow_partial_results: bool,
+/// Find the source files used by the lib, bin and build script targets of all
+/// packages by resolving their module trees, without building anything.
+/// Modules excluded by `#[cfg(..)]` attributes are not followed when the
+/// target cfgs are known. Files that can not be resolved are reported as
+/// warnings.
+pub fn resolve_rs_files_from_module_trees(
     cargo_metadata_parameters: &CargoMetadataParameters,
+    cfgs: Option<&[Cfg]>,
+    config: &Config,
+) -> Result<HashSet<PathBuf>, CliError> {
+    let cfg_set = cfgs
+        .map(ToGeigerCfgSet::to_geiger_cfg_set)
+        .unwrap_or_default();
+    let packages = &cargo_metadata_parameters.metadata.packages;
+    let package_id_to_cfg_set =
+        package_cfg_sets(cargo_metadata_parameters, &cfg_set, packages);
+    let module_trees = packages
+        .par_iter()
+        .flat_map_iter(|package| {
+            package
+                .targets
+                .iter()
+                .filter(|target| {
+                    matches!(
+                        into_target_kind(target.kind.clone()),
+                        TargetKind::Bin
+                            | TargetKind::CustomBuild
+                            | TargetKind::Lib(_)
+                    ) && target.src_path.exists()
+                })
+                .map(|target| {
+                    resolve_module_tree(
+                        target.src_path.as_std_path(),
+                        &package_id_to_cfg_set[&package.id],
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut rs_files_used = HashSet::new();
+    for module_tree in module_trees {
+        for error in &module_tree.errors {
+            config
+                .shell()
+                .warn(format!("Failed to resolve module: {}", error))?;
+        }
+        rs_files_used.extend(module_tree.files);
+    }
+    Ok(rs_files_used)
+}
+
+/// What to scan, and how, in the source files of all packages.
+struct FindParameters<'a> {
+    allow_partial_results: bool,
+    cargo_metadata_parameters: &'a CargoMetadataParameters<'a>,
+    cfg_set: &'a CfgSet,
+    dangerous_api_rules: &'a DangerousApiRules,
+    generated_rs_files: &'a HashMap<PathBuf, PathBuf>,
     include_tests: IncludeTests,
     mode: ScanMode,
+}
+
+fn find_unsafe_in_packages_with_progress<F>(
+    find_parameters: FindParameters,
     mut progress_fn: F,
 ) -> GeigerContext
 where
@@ -60,10 +143,7 @@ where
     in_place_scope(|s| {
         s.spawn(|_| {
             res = Some(find_unsafe_in_packages(
-                allow_partial_results,
-                cargo_metadata_parameters,
-                include_tests,
-                mode,
+                find_parameters,
                 Some(on_processed),
             ))
         });
@@ -76,37 +156,81 @@ where
 }
 
 fn find_unsafe_in_packages<F>(
-    allow_partial_results: bool,
-    cargo_metadata_parameters: &CargoMetadataParameters,
-    include_tests: IncludeTests,
-    mode: ScanMode,
+    find_parameters: FindParameters,
     on_processed: Option<F>,
 ) -> GeigerContext
 where
     F: Fn(usize, usize) + Send + Sync,
 {
+    let FindParameters {
+        allow_partial_results,
+        cargo_metadata_parameters,
+        cfg_set,
+        dangerous_api_rules,
+        generated_rs_files,
+        include_tests,
+        mode,
+    } = find_parameters;
     let package_id_to_metrics = Arc::new(Mutex::new(HashMap::new()));
-    let ignored = Arc::new(Mutex::new(HashSet::new()));
     let packages = cargo_metadata_parameters.metadata.packages.to_vec();
-    let package_code_files: Vec<_> =
-        find_rs_files_in_packages(&packages).collect();
+    let package_id_to_cfg_set =
+        package_cfg_sets(cargo_metadata_parameters, cfg_set, &packages);
+    let build_script_paths =
+        find_build_script_paths(&packages, &package_id_to_cfg_set);
+    // Build scripts are compiled for the host, whose cfgs are not known
+    let build_script_cfg_set = CfgSet::default();
+    let generated_rs_file_package_ids =
+        generated_rs_file_package_ids(generated_rs_files, &packages);
+    // Generated files are only attributed to the package including them,
+    // even when they are located in the directory of another package
+    let package_code_files: Vec<_> = find_rs_files_in_packages(&packages)
+        .filter(|(_, rs_code_file)| {
+            !matches!(
+                rs_code_file,
+                RsFile::Other(path_buf)
+                    if generated_rs_file_package_ids.contains_key(path_buf)
+            )
+        })
+        .chain(generated_rs_file_package_ids.iter().map(
+            |(path_buf, package_id)| {
+                (package_id.clone(), RsFile::Other(path_buf.clone()))
+            },
+        ))
+        .collect();
     let package_code_file_count = package_code_files.len();
     let processed_count = AtomicUsize::new(0);
     package_code_files.into_par_iter().for_each_with(
-        (package_id_to_metrics.clone(), ignored.clone()),
-        |(package_id_to_metrics, ignored), (package_id, rs_code_file)| {
-            if let RsFile::CustomBuildRoot(path_buf) = rs_code_file {
-                let mut ignored = ignored.lock().unwrap();
-                ignored.insert(path_buf);
-                return;
-            }
+        package_id_to_metrics.clone(),
+        |package_id_to_metrics, (package_id, rs_code_file)| {
+            let is_build_script =
+                matches!(rs_code_file, RsFile::CustomBuildRoot(_));
             let (is_entry_point, path_buf) =
                 into_is_entry_point_and_path_buf(rs_code_file);
+            let is_shared_with_crate = build_script_paths.get(&path_buf);
+            let is_build_script =
+                is_build_script || is_shared_with_crate.is_some();
+            let is_shared_with_crate =
+                is_shared_with_crate.copied().unwrap_or(false);
+            let is_generated =
+                generated_rs_file_package_ids.contains_key(&path_buf);
+            // Build scripts are reported separately, their entry points do
+            // not decide whether the package forbids unsafe code
+            let is_entry_point = is_entry_point && !is_build_script;
             if let (false, ScanMode::EntryPointsOnly) = (is_entry_point, &mode)
             {
                 return;
             }
-            match find_unsafe_in_file(&path_buf, include_tests) {
+            let cfg_set = if is_build_script {
+                &build_script_cfg_set
+            } else {
+                &package_id_to_cfg_set[&package_id]
+            };
+            match find_unsafe_in_file(
+                &path_buf,
+                include_tests,
+                cfg_set,
+                dangerous_api_rules,
+            ) {
                 Err(error) => {
                     handle_unsafe_in_file_error(
                         allow_partial_results,
@@ -115,14 +239,30 @@ where
                     );
                 }
                 Ok(rs_file_metrics) => {
+                    if rs_file_metrics.approximate {
+                        eprintln!(
+                            "Failed to parse file: {}, {}, counted unsafe \
+                             usage approximately from its tokens",
+                            path_buf.display(),
+                            rs_file_metrics
+                                .parse_error
+                                .as_deref()
+                                .unwrap_or("unknown error")
+                        );
+                    }
                     let package_id_to_metrics =
                         &mut package_id_to_metrics.lock().unwrap();
                     update_package_id_to_metrics_with_rs_file_metrics(
-                        is_entry_point,
                         package_id,
                         package_id_to_metrics,
                         path_buf,
-                        rs_file_metrics,
+                        RsFileMetricsWrapper {
+                            metrics: rs_file_metrics,
+                            is_crate_entry_point: is_entry_point,
+                            is_build_script,
+                            is_shared_with_crate,
+                            is_generated,
+                        },
                     );
                 }
             }
@@ -136,7 +276,7 @@ where
         },
     );
 
-    let cargo_core_package_metrics = package_id_to_metrics
+    let mut cargo_core_package_metrics = package_id_to_metrics
         .lock()
         .unwrap()
         .iter()
@@ -145,12 +285,130 @@ where
         })
         .collect::<HashMap<PackageId, PackageMetrics>>();
 
+    for package in &packages {
+        if let Some(package_metrics) =
+            cargo_core_package_metrics.get_mut(&package.id)
+        {
+            package_metrics.manifest_lint_status =
+                manifest_lint_status(package.manifest_path.as_std_path());
+        }
+    }
+
     GeigerContext {
         package_id_to_metrics: cargo_core_package_metrics,
-        ignored_paths: Arc::try_unwrap(ignored).unwrap().into_inner().unwrap(),
     }
 }
 
+/// The source files of the build scripts of all packages, found by resolving
+/// their module trees, mapped to whether they are also part of the module
+/// trees of the other targets of their package, like a module shared through
+/// a `#[path = ".."]` attribute. Build scripts are compiled for the host, so
+/// their modules are followed whatever their `#[cfg(..)]` attributes. The
+/// paths are canonicalized, like the paths of the scanned files.
+fn find_build_script_paths(
+    packages: &[cargo_metadata::Package],
+    package_id_to_cfg_set: &HashMap<PackageId, CfgSet>,
+) -> HashMap<PathBuf, bool> {
+    packages
+        .par_iter()
+        .flat_map_iter(|package| {
+            let (build_script_targets, crate_targets): (Vec<_>, Vec<_>) =
+                package
+                    .targets
+                    .iter()
+                    .filter_map(|target| {
+                        // A package published to crates.io is not required to
+                        // include every target
+                        let src_path = target.src_path.canonicalize().ok()?;
+                        let is_build_script = matches!(
+                            into_target_kind(target.kind.clone()),
+                            TargetKind::CustomBuild
+                        );
+                        Some((is_build_script, src_path))
+                    })
+                    .partition(|(is_build_script, _)| *is_build_script);
+            if build_script_targets.is_empty() {
+                return Vec::new();
+            }
+            let module_files =
+                |targets: Vec<(bool, PathBuf)>, cfg_set: &CfgSet| {
+                    targets
+                        .into_iter()
+                        .flat_map(|(_, src_path)| {
+                            resolve_module_tree(&src_path, cfg_set).files
+                        })
+                        .collect::<HashSet<_>>()
+                };
+            let crate_cfg_set = package_id_to_cfg_set
+                .get(&package.id)
+                .cloned()
+                .unwrap_or_default();
+            let crate_files = module_files(crate_targets, &crate_cfg_set);
+            module_files(build_script_targets, &CfgSet::default())
+                .into_iter()
+                .map(|path_buf| {
+                    let is_shared_with_crate = crate_files.contains(&path_buf);
+                    (path_buf, is_shared_with_crate)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The package of each canonicalized manifest directory, which cargo passes
+/// to rustc and build scripts.
+pub fn manifest_dir_package_ids(
+    packages: &[cargo_metadata::Package],
+) -> HashMap<PathBuf, PackageId> {
+    packages
+        .iter()
+        .filter_map(|package| {
+            let manifest_dir =
+                package.manifest_path.parent()?.canonicalize().ok()?;
+            Some((manifest_dir, package.id.clone()))
+        })
+        .collect()
+}
+
+/// The package of each generated source file, found from the manifest
+/// directory of the package it was generated for. Files of unknown packages
+/// are left out.
+fn generated_rs_file_package_ids(
+    generated_rs_files: &HashMap<PathBuf, PathBuf>,
+    packages: &[cargo_metadata::Package],
+) -> HashMap<PathBuf, PackageId> {
+    let manifest_dir_to_package_id = manifest_dir_package_ids(packages);
+    generated_rs_files
+        .iter()
+        .filter_map(|(path_buf, manifest_dir)| {
+            manifest_dir_to_package_id
+                .get(manifest_dir)
+                .map(|package_id| (path_buf.clone(), package_id.clone()))
+        })
+        .collect()
+}
+
+/// The target cfg set of every package, extended with the features enabled
+/// for the package. Features are left unknown when they were not resolved.
+fn package_cfg_sets(
+    cargo_metadata_parameters: &CargoMetadataParameters,
+    cfg_set: &CfgSet,
+    packages: &[cargo_metadata::Package],
+) -> HashMap<PackageId, CfgSet> {
+    let resolved_features =
+        cargo_metadata_parameters.metadata.get_resolved_features();
+    packages
+        .iter()
+        .map(|package| {
+            let mut package_cfg_set = cfg_set.clone();
+            if let Some(features) = resolved_features.get(&package.id) {
+                package_cfg_set.insert_features(features.iter().cloned());
+            }
+            (package.id.clone(), package_cfg_set)
+        })
+        .collect()
+}
+
 fn find_rs_files_in_dir(dir: &Path) -> impl Iterator<Item = PathBuf> {
     let walker = WalkDir::new(dir).into_iter();
     walker.filter_map(|entry| {
@@ -230,21 +488,15 @@ fn handle_unsafe_in_file_error(
 }
 
 fn update_package_id_to_metrics_with_rs_file_metrics(
-    is_entry_point: bool,
     package_id: PackageId,
     package_id_to_metrics: &mut HashMap<PackageId, PackageMetrics>,
     path_buf: PathBuf,
-    rs_file_metrics: RsFileMetrics,
+    wrapper: RsFileMetricsWrapper,
 ) {
     let package_metrics = package_id_to_metrics
         .entry(package_id)
         .or_insert_with(PackageMetrics::default);
-    let wrapper = package_metrics
-        .rs_path_to_metrics
-        .entry(path_buf)
-        .or_insert_with(RsFileMetricsWrapper::default);
-    wrapper.metrics = rs_file_metrics;
-    wrapper.is_crate_entry_point = is_entry_point;
+    package_metrics.rs_path_to_metrics.insert(path_buf, wrapper);
 }
 
 #[cfg(test)]
@@ -253,7 +505,7 @@ mod find_tests {
 
     use cargo_metadata::{CargoOpt, MetadataCommand};
     use rstest::*;
-    use std::fs::File;
+    use std::fs::{self, File};
     use std::io;
     use std::io::ErrorKind;
     use tempfile::tempdir;
@@ -344,14 +596,47 @@ mod find_tests {
 
     #[rstest(
         input_is_entry_point,
+        input_is_build_script,
+        input_is_generated,
         expected_is_crate_entry_point,
+        expected_is_build_script,
+        expected_is_generated,
         package,
-        case(true, true, get_current_workspace_package()),
-        case(false, false, get_current_workspace_package())
+        case(
+            true,
+            false,
+            false,
+            true,
+            false,
+            false,
+            get_current_workspace_package()
+        ),
+        case(
+            false,
+            true,
+            false,
+            false,
+            true,
+            false,
+            get_current_workspace_package()
+        ),
+        case(
+            false,
+            false,
+            true,
+            false,
+            false,
+            true,
+            get_current_workspace_package()
+        )
     )]
     fn update_package_id_to_metrics_with_rs_file_metrics_test(
         input_is_entry_point: bool,
+        input_is_build_script: bool,
+        input_is_generated: bool,
         expected_is_crate_entry_point: bool,
+        expected_is_build_script: bool,
+        expected_is_generated: bool,
         package: cargo_metadata::Package,
     ) {
         let mut package_id_to_metrics =
@@ -361,15 +646,25 @@ mod find_tests {
         let rs_file = rs_files_in_package.pop().unwrap();
         let (_, path_buf) = into_is_entry_point_and_path_buf(rs_file);
 
-        let rs_file_metrics =
-            find_unsafe_in_file(path_buf.as_path(), IncludeTests::Yes).unwrap();
+        let rs_file_metrics = find_unsafe_in_file(
+            path_buf.as_path(),
+            IncludeTests::Yes,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
 
         update_package_id_to_metrics_with_rs_file_metrics(
-            input_is_entry_point,
             package.id.clone(),
             &mut package_id_to_metrics,
             package.manifest_path.clone().into(),
-            rs_file_metrics.clone(),
+            RsFileMetricsWrapper {
+                metrics: rs_file_metrics.clone(),
+                is_crate_entry_point: input_is_entry_point,
+                is_build_script: input_is_build_script,
+                is_shared_with_crate: false,
+                is_generated: input_is_generated,
+            },
         );
 
         assert!(package_id_to_metrics.contains_key(&package.id));
@@ -383,6 +678,102 @@ mod find_tests {
 
         assert_eq!(wrapper.metrics, rs_file_metrics);
         assert_eq!(wrapper.is_crate_entry_point, expected_is_crate_entry_point);
+        assert_eq!(wrapper.is_build_script, expected_is_build_script);
+        assert_eq!(wrapper.is_generated, expected_is_generated);
+    }
+
+    #[rstest]
+    fn generated_rs_file_package_ids_test() {
+        let package = get_current_workspace_package();
+        let manifest_dir = package
+            .manifest_path
+            .parent()
+            .unwrap()
+            .canonicalize()
+            .unwrap();
+        let generated_rs_files = vec![
+            (PathBuf::from("/out/bindings.rs"), manifest_dir),
+            (
+                PathBuf::from("/other/out/bindings.rs"),
+                PathBuf::from("/other"),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            generated_rs_file_package_ids(
+                &generated_rs_files,
+                &[package.clone()]
+            ),
+            vec![(PathBuf::from("/out/bindings.rs"), package.id)]
+                .into_iter()
+                .collect::<HashMap<_, _>>()
+        );
+    }
+
+    #[rstest]
+    fn find_build_script_paths_test() {
+        let manifest_path = Path::new(
+            "../test_crates/test8_package_with_build_rs_no_deps/Cargo.toml",
+        );
+        let metadata = MetadataCommand::new()
+            .manifest_path(manifest_path)
+            .exec()
+            .unwrap();
+
+        let build_script_paths =
+            find_build_script_paths(&metadata.packages, &HashMap::new());
+
+        let build_script_path = manifest_path
+            .with_file_name("build.rs")
+            .canonicalize()
+            .unwrap();
+        assert_eq!(
+            build_script_paths,
+            vec![(build_script_path, false)]
+                .into_iter()
+                .collect::<HashMap<_, _>>()
+        );
+    }
+
+    #[rstest]
+    fn find_build_script_paths_test_shared_module() {
+        let temp_dir = tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = temp_dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[package]\nname = \"shared\"\nversion = \"0.1.0\"\n",
+        );
+        write(
+            "build.rs",
+            "#[path = \"src/shared.rs\"]\nmod shared;\nfn main() {}",
+        );
+        write("src/lib.rs", "mod shared;");
+        write("src/shared.rs", "pub fn f() {}");
+        let metadata = MetadataCommand::new()
+            .manifest_path(temp_dir.path().join("Cargo.toml"))
+            .no_deps()
+            .exec()
+            .unwrap();
+
+        let build_script_paths =
+            find_build_script_paths(&metadata.packages, &HashMap::new());
+
+        let root = temp_dir.path().canonicalize().unwrap();
+        assert_eq!(
+            build_script_paths,
+            vec![
+                (root.join("build.rs"), false),
+                (root.join("src").join("shared.rs"), true)
+            ]
+            .into_iter()
+            .collect::<HashMap<_, _>>()
+        );
     }
 
     #[fixture]
@@ -5,13 +5,17 @@ use crate::graph::Graph;
 use crate::mapping::CargoMetadataParameters;
 
 use super::find::find_unsafe;
-use super::{package_metrics, ScanMode, ScanParameters, ScanResult};
+use super::{
+    crate_lint_status, package_metrics, ScanMode, ScanParameters, ScanResult,
+};
 
 use table::scan_forbid_to_table;
 
 use cargo::{CliError, Config};
-use cargo_geiger_serde::{QuickReportEntry, QuickSafetyReport};
+use cargo_geiger_serde::{LintStatus, QuickReportEntry, QuickSafetyReport};
 use cargo_metadata::PackageId;
+use geiger::DangerousApiRules;
+use std::collections::HashMap;
 
 pub fn scan_forbid_unsafe(
     cargo_metadata_parameters: &CargoMetadataParameters,
@@ -48,7 +52,10 @@ fn scan_forbid_to_report(
 ) -> Result<ScanResult, CliError> {
     let geiger_context = find_unsafe(
         cargo_metadata_parameters,
+        graph.cfgs.as_deref(),
+        &DangerousApiRules::new(),
         config,
+        &HashMap::new(),
         ScanMode::EntryPointsOnly,
         print_config,
     )?;
@@ -70,10 +77,12 @@ fn scan_forbid_to_report(
             |(_, rs_file_metrics_wrapper)| {
                 rs_file_metrics_wrapper.metrics.forbids_unsafe
             },
-        );
+        ) || pack_metrics.manifest_lint_status
+            == LintStatus::Forbid;
         let entry = QuickReportEntry {
             package,
             forbids_unsafe,
+            lint_status: crate_lint_status(&pack_metrics),
         };
         report.packages.insert(entry.package.id.clone(), entry);
     }
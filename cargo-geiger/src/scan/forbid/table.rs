@@ -9,11 +9,14 @@ use crate::tree::traversal::walk_dependency_tree;
 use crate::tree::TextTreeLine;
 
 use super::super::find::find_unsafe;
-use super::super::{ScanMode, ScanResult};
+use super::super::{crate_lint_status, ScanMode, ScanResult};
 
 use cargo::{CliError, Config};
+use cargo_geiger_serde::LintStatus;
 use cargo_metadata::PackageId;
 use colored::Colorize;
+use geiger::DangerousApiRules;
+use std::collections::HashMap;
 
 pub fn scan_forbid_to_table(
     cargo_metadata_parameters: &CargoMetadataParameters,
@@ -52,7 +55,10 @@ pub fn scan_forbid_to_table(
             } => {
                 let geiger_ctx = find_unsafe(
                     cargo_metadata_parameters,
+                    graph.cfgs.as_deref(),
+                    &DangerousApiRules::new(),
                     config,
+                    &HashMap::new(),
                     ScanMode::EntryPointsOnly,
                     print_config,
                 )?;
@@ -80,10 +86,13 @@ fn construct_key_lines(emoji_symbols: &EmojiSymbols) -> Vec<String> {
     let mut output_key_lines = vec![String::new(), String::from("Symbols: ")];
 
     let forbids = "All entry point .rs files declare #![forbid(unsafe_code)].";
+    let denies = "All entry point .rs files declare #![deny(unsafe_code)], or \
+                  #![forbid(unsafe_code)] in a cfg_attr.";
     let unknown = "This crate may use unsafe code.";
 
     let symbol_kinds_to_string_values = vec![
         (SymbolKind::Lock, forbids),
+        (SymbolKind::NoEntry, denies),
         (SymbolKind::QuestionMark, unknown),
     ];
 
@@ -117,6 +126,7 @@ fn handle_package_text_tree_line(
     tree_vines: String,
 ) {
     let sym_lock = emoji_symbols.emoji(SymbolKind::Lock);
+    let sym_no_entry = emoji_symbols.emoji(SymbolKind::NoEntry);
     let sym_qmark = emoji_symbols.emoji(SymbolKind::QuestionMark);
 
     let name = format_package_name(
@@ -125,18 +135,16 @@ fn handle_package_text_tree_line(
         &print_config.format,
     );
     let package_metrics = geiger_ctx.package_id_to_metrics.get(&package_id);
-    let package_forbids_unsafe = match package_metrics {
-        None => false, // no metrics available, .rs parsing failed?
-        Some(package_metric) => package_metric.rs_path_to_metrics.iter().all(
-            |(_k, rs_file_metrics_wrapper)| {
-                rs_file_metrics_wrapper.metrics.forbids_unsafe
-            },
-        ),
+    let package_lint_status = match package_metrics {
+        None => LintStatus::Allow, // no metrics available, .rs parsing failed?
+        Some(package_metric) => crate_lint_status(package_metric),
     };
-    let (symbol, name) = if package_forbids_unsafe {
-        (&sym_lock, name.green())
-    } else {
-        (&sym_qmark, name.red())
+    let (symbol, name) = match package_lint_status {
+        LintStatus::Forbid => (&sym_lock, name.green()),
+        LintStatus::Deny
+        | LintStatus::ConditionalDeny
+        | LintStatus::ConditionalForbid => (&sym_no_entry, name.cyan()),
+        _ => (&sym_qmark, name.red()),
     };
     scan_output_lines.push(format!("{} {}{}", symbol, tree_vines, name));
 }
@@ -152,6 +160,6 @@ mod forbid_tests {
         let emoji_symbols = EmojiSymbols::new(OutputFormat::Utf8);
         let output_key_lines = construct_key_lines(&emoji_symbols);
 
-        assert_eq!(output_key_lines.len(), 5);
+        assert_eq!(output_key_lines.len(), 6);
     }
 }
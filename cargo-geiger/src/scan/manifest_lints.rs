@@ -0,0 +1,206 @@
+use cargo_geiger_serde::LintStatus;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// The level of the `unsafe_code` lint declared in the `[lints.rust]` table
+/// of a package manifest. A package declaring `lints.workspace = true`
+/// inherits the `[workspace.lints.rust]` table of the manifest of its own
+/// workspace, which is not the workspace being scanned for registry and git
+/// dependencies. Manifests that can not be read or parsed are treated as not
+/// declaring the lint, leaving the source attributes to decide.
+pub fn manifest_lint_status(manifest_path: &Path) -> LintStatus {
+    let manifest = match read_manifest(manifest_path) {
+        Some(manifest) => manifest,
+        None => return LintStatus::Allow,
+    };
+    let workspace_manifest = if inherits_workspace_lints(&manifest) {
+        find_workspace_manifest(manifest_path, &manifest)
+    } else {
+        None
+    };
+    lint_status_from_manifest(&manifest, workspace_manifest.as_ref())
+}
+
+/// The manifest of the workspace of a package, found the way Cargo does: the
+/// one the `package.workspace` key points to, or else the nearest manifest
+/// with a `[workspace]` table, starting from the manifest of the package and
+/// walking up the parent directories.
+fn find_workspace_manifest(
+    manifest_path: &Path,
+    manifest: &Table,
+) -> Option<Table> {
+    if manifest.contains_key("workspace") {
+        return Some(manifest.clone());
+    }
+    let package_dir = manifest_path.parent()?;
+    if let Some(workspace_dir) = manifest
+        .get("package")
+        .and_then(|package| package.get("workspace"))
+        .and_then(Value::as_str)
+    {
+        return read_manifest(
+            &package_dir.join(workspace_dir).join("Cargo.toml"),
+        );
+    }
+    package_dir
+        .ancestors()
+        .skip(1)
+        .filter_map(|dir| read_manifest(&dir.join("Cargo.toml")))
+        .find(|manifest| manifest.contains_key("workspace"))
+}
+
+fn read_manifest(manifest_path: &Path) -> Option<Table> {
+    let content = fs::read_to_string(manifest_path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+fn inherits_workspace_lints(manifest: &Table) -> bool {
+    manifest
+        .get("lints")
+        .and_then(|lints| lints.get("workspace"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn lint_status_from_manifest(
+    manifest: &Table,
+    workspace_manifest: Option<&Table>,
+) -> LintStatus {
+    let lints = if inherits_workspace_lints(manifest) {
+        workspace_manifest
+            .and_then(|workspace_manifest| workspace_manifest.get("workspace"))
+            .and_then(|workspace| workspace.get("lints"))
+    } else {
+        manifest.get("lints")
+    };
+    lints
+        .and_then(|lints| lints.get("rust"))
+        .and_then(|rust_lints| rust_lints.get("unsafe_code"))
+        .map(unsafe_code_lint_status)
+        .unwrap_or_default()
+}
+
+/// The lint level is either given directly as a string, or in the `level`
+/// field of a table that also sets the `priority` of the lint.
+fn unsafe_code_lint_status(unsafe_code: &Value) -> LintStatus {
+    let level = match unsafe_code {
+        Value::Table(table) => table.get("level").and_then(Value::as_str),
+        value => value.as_str(),
+    };
+    match level {
+        Some("forbid") => LintStatus::Forbid,
+        Some("deny") => LintStatus::Deny,
+        _ => LintStatus::Allow,
+    }
+}
+
+#[cfg(test)]
+mod manifest_lints_tests {
+    use super::*;
+
+    use rstest::*;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[rstest(
+        input_manifest,
+        expected_lint_status,
+        case("[package]\nname = \"foo\"", LintStatus::Allow),
+        case("[lints.rust]\nunsafe_code = \"forbid\"", LintStatus::Forbid),
+        case("[lints.rust]\nunsafe_code = \"deny\"", LintStatus::Deny),
+        case("[lints.rust]\nunsafe_code = \"warn\"", LintStatus::Allow),
+        case(
+            "[lints.rust]\nunsafe_code = { level = \"forbid\", priority = 1 }",
+            LintStatus::Forbid
+        ),
+        case("[lints.clippy]\nunsafe_code = \"forbid\"", LintStatus::Allow),
+        case("[lints]\nworkspace = true", LintStatus::Allow)
+    )]
+    fn lint_status_from_manifest_test(
+        input_manifest: &str,
+        expected_lint_status: LintStatus,
+    ) {
+        let manifest = toml::from_str(input_manifest).unwrap();
+
+        assert_eq!(
+            lint_status_from_manifest(&manifest, None),
+            expected_lint_status
+        );
+    }
+
+    #[rstest]
+    fn manifest_lint_status_test_inherits_workspace_lints() {
+        let temp_dir = tempdir().unwrap();
+        let workspace_manifest_path = write_manifest(
+            temp_dir.path(),
+            "[workspace]\nmembers = [\"member\"]\n\n\
+             [workspace.lints.rust]\nunsafe_code = \"forbid\"",
+        );
+        let member_manifest_path = write_manifest(
+            &temp_dir.path().join("member"),
+            "[package]\nname = \"member\"\n\n[lints]\nworkspace = true",
+        );
+
+        assert_eq!(
+            manifest_lint_status(&member_manifest_path),
+            LintStatus::Forbid
+        );
+        assert_eq!(
+            manifest_lint_status(&workspace_manifest_path),
+            LintStatus::Allow
+        );
+    }
+
+    #[rstest]
+    fn manifest_lint_status_test_dependency_inherits_its_own_workspace_lints() {
+        let temp_dir = tempdir().unwrap();
+        write_manifest(
+            temp_dir.path(),
+            "[workspace]\nmembers = [\"member\"]\n\n\
+             [workspace.lints.rust]\nunsafe_code = \"forbid\"",
+        );
+        let member_manifest_path = write_manifest(
+            &temp_dir.path().join("member"),
+            "[package]\nname = \"member\"\n\n[lints]\nworkspace = true",
+        );
+        let dependency_workspace_dir = temp_dir.path().join("git").join("dep");
+        write_manifest(
+            &dependency_workspace_dir,
+            "[workspace]\nmembers = [\"crates/*\"]\n\n\
+             [workspace.lints.rust]\nunsafe_code = \"deny\"",
+        );
+        let dependency_manifest_path = write_manifest(
+            &dependency_workspace_dir.join("crates").join("dep"),
+            "[package]\nname = \"dep\"\n\n[lints]\nworkspace = true",
+        );
+        let explicit_dependency_manifest_path = write_manifest(
+            &temp_dir.path().join("git").join("explicit"),
+            "[package]\nname = \"explicit\"\nworkspace = \"../dep\"\n\n\
+             [lints]\nworkspace = true",
+        );
+
+        assert_eq!(
+            manifest_lint_status(&member_manifest_path),
+            LintStatus::Forbid
+        );
+        assert_eq!(
+            manifest_lint_status(&dependency_manifest_path),
+            LintStatus::Deny
+        );
+        assert_eq!(
+            manifest_lint_status(&explicit_dependency_manifest_path),
+            LintStatus::Deny
+        );
+    }
+
+    fn write_manifest(dir: &Path, content: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let manifest_path = dir.join("Cargo.toml");
+        let mut manifest = File::create(&manifest_path).unwrap();
+        writeln!(manifest, "{}", content).unwrap();
+        manifest_path
+    }
+}
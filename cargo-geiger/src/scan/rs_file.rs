@@ -1,4 +1,5 @@
 mod custom_executor;
+mod unsafe_code_lints;
 
 use custom_executor::{CustomExecutor, CustomExecutorInnerContext};
 
@@ -9,12 +10,14 @@ use cargo::ops;
 use cargo::ops::{CleanOptions, CompileOptions};
 use cargo::util::{interning::InternedString, CargoResult};
 use cargo::Config;
+use cargo_geiger_serde::CounterBlock;
 use cargo_util::paths;
 use geiger::RsFileMetrics;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::io;
+use std::ops::Add;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, PoisonError};
 use walkdir::{DirEntry, WalkDir};
@@ -27,7 +30,7 @@ pub enum RsFile {
     /// Executable entry point source file, usually src/main.rs
     BinRoot(PathBuf),
 
-    /// Not sure if this is relevant but let's be conservative for now.
+    /// Build script entry point source file, usually build.rs
     CustomBuildRoot(PathBuf),
 
     /// Library entry point source file, usually src/lib.rs
@@ -47,6 +50,46 @@ pub struct RsFileMetricsWrapper {
     /// and cannot know if a file is a crate entry point or not, so we add this
     /// information here.
     pub is_crate_entry_point: bool,
+
+    /// Whether the file belongs to the build script of its package, whose
+    /// unsafe usage is reported separately from the crate code.
+    pub is_build_script: bool,
+
+    /// Whether a file of the build script is also a module of the other
+    /// targets of its package, so that it is counted as crate code as well.
+    pub is_shared_with_crate: bool,
+
+    /// Whether the file was generated into the `OUT_DIR` of the build script
+    /// of its package, whose unsafe usage is reported separately from the
+    /// hand written crate code.
+    pub is_generated: bool,
+}
+
+/// The `.rs` files used by a build.
+#[derive(Debug, Default)]
+pub struct RsFileDeps {
+    /// All `.rs` files used by the build, canonicalized.
+    pub rs_files_used: HashSet<PathBuf>,
+
+    /// The used `.rs` files that build scripts generated into their
+    /// `OUT_DIR`, mapped to the canonicalized manifest directory of the
+    /// package which includes them.
+    pub generated_rs_files: HashMap<PathBuf, PathBuf>,
+
+    /// The `unsafe` usage reported by the `unsafe_code` lint of rustc, by
+    /// canonicalized manifest directory of the package, only populated
+    /// when requested.
+    pub unsafe_code_lints: HashMap<PathBuf, CounterBlock>,
+
+    /// The `unsafe` usage found in the macro expanded source of the crates,
+    /// by canonicalized manifest directory of the package, only populated
+    /// when requested. Packages with a crate that failed to expand are left
+    /// out.
+    pub expanded: HashMap<PathBuf, CounterBlock>,
+
+    /// Why macro expansion failed, by canonicalized manifest directory of
+    /// the package, when requested.
+    pub macro_expansion_errors: HashMap<PathBuf, String>,
 }
 
 #[derive(Debug)]
@@ -146,11 +189,17 @@ pub fn is_file_with_ext(entry: &DirEntry, file_ext: &str) -> bool {
 }
 
 /// Trigger a `cargo clean` + `cargo check` and listen to the cargo/rustc
-/// communication to figure out which source files were used by the build.
+/// communication to figure out which source files were used by the build,
+/// and which of them were generated by build scripts. With
+/// `lint_unsafe_code`, the `unsafe_code` lint diagnostics of rustc are
+/// counted as well, and with `expand_macros` the macro expanded source of
+/// the crates is scanned.
 pub fn resolve_rs_file_deps(
     compile_options: &CompileOptions,
+    expand_macros: bool,
+    lint_unsafe_code: bool,
     workspace: &Workspace,
-) -> Result<HashSet<PathBuf>, RsResolveError> {
+) -> Result<RsFileDeps, RsResolveError> {
     let config = workspace.config();
     let (pkg_set, _) = ops::resolve_ws(workspace)
         .map_err(|e| RsResolveError::Cargo(e.to_string()))?;
@@ -181,7 +230,9 @@ pub fn resolve_rs_file_deps(
         compile_with_exec(
             compile_options,
             config,
+            expand_macros,
             inner_arc.clone(),
+            lint_unsafe_code,
             workspace,
         )?;
     }
@@ -189,10 +240,14 @@ pub fn resolve_rs_file_deps(
     let workspace_root = workspace.root().to_path_buf();
     let inner_mutex =
         Arc::try_unwrap(inner_arc).map_err(|_| RsResolveError::ArcUnwrap())?;
-    let (rs_files, out_dir_args) = {
-        let ctx = inner_mutex.into_inner()?;
-        (ctx.rs_file_args, ctx.out_dir_args)
-    };
+    let CustomExecutorInnerContext {
+        rs_file_args: rs_files,
+        out_dir_args,
+        generated_out_dirs,
+        unsafe_code_lints,
+        expanded,
+        macro_expansion_errors,
+    } = inner_mutex.into_inner()?;
     let mut path_buf_hash_set = HashSet::<PathBuf>::new();
     for out_dir in out_dir_args {
         // TODO: Figure out if the `.d` dep files are used by one or more rustc
@@ -209,8 +264,47 @@ pub fn resolve_rs_file_deps(
         // rs_files must already be canonicalized
         path_buf_hash_set.insert(path_buf);
     }
+    let generated_rs_files =
+        generated_rs_files(&generated_out_dirs, &path_buf_hash_set);
+    let expanded = expanded
+        .into_iter()
+        .filter(|(manifest_dir, _)| {
+            !macro_expansion_errors.contains_key(manifest_dir)
+        })
+        .map(|(manifest_dir, targets)| {
+            (
+                manifest_dir,
+                targets.into_values().fold(Default::default(), Add::add),
+            )
+        })
+        .collect();
+
+    Ok(RsFileDeps {
+        rs_files_used: path_buf_hash_set,
+        generated_rs_files,
+        unsafe_code_lints,
+        expanded,
+        macro_expansion_errors,
+    })
+}
 
-    Ok(path_buf_hash_set)
+/// The files located in the `OUT_DIR` of a build script, mapped to the
+/// manifest directory of the package compiled with that `OUT_DIR`.
+fn generated_rs_files(
+    generated_out_dirs: &HashMap<PathBuf, PathBuf>,
+    rs_files_used: &HashSet<PathBuf>,
+) -> HashMap<PathBuf, PathBuf> {
+    rs_files_used
+        .iter()
+        .filter_map(|path_buf| {
+            generated_out_dirs
+                .iter()
+                .find(|(out_dir, _)| path_buf.starts_with(out_dir))
+                .map(|(_, manifest_dir)| {
+                    (path_buf.clone(), manifest_dir.clone())
+                })
+        })
+        .collect()
 }
 
 fn add_dir_entries_to_path_buf_hash_set(
@@ -243,12 +337,16 @@ fn add_dir_entries_to_path_buf_hash_set(
 fn compile_with_exec(
     compile_options: &CompileOptions,
     config: &Config,
+    expand_macros: bool,
     inner_arc: Arc<Mutex<CustomExecutorInnerContext>>,
+    lint_unsafe_code: bool,
     workspace: &Workspace,
 ) -> Result<(), RsResolveError> {
     let custom_executor = CustomExecutor {
         cwd: config.cwd().to_path_buf(),
         inner_ctx: inner_arc,
+        lint_unsafe_code,
+        expand_macros,
     };
 
     let custom_executor_arc: Arc<dyn Executor> = Arc::new(custom_executor);
@@ -418,6 +516,34 @@ mod rs_file_tests {
         );
     }
 
+    #[rstest]
+    fn generated_rs_files_test() {
+        let generated_out_dirs = vec![(
+            PathBuf::from("/target/debug/build/package-hash/out"),
+            PathBuf::from("/package"),
+        )]
+        .into_iter()
+        .collect();
+        let rs_files_used = vec![
+            PathBuf::from("/package/src/lib.rs"),
+            PathBuf::from("/target/debug/build/package-hash/out/bindings.rs"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            generated_rs_files(&generated_out_dirs, &rs_files_used),
+            vec![(
+                PathBuf::from(
+                    "/target/debug/build/package-hash/out/bindings.rs"
+                ),
+                PathBuf::from("/package"),
+            )]
+            .into_iter()
+            .collect::<HashMap<_, _>>()
+        );
+    }
+
     #[rstest]
     fn is_file_with_ext_test() {
         let config = Config::default().unwrap();
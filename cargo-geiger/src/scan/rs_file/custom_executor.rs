@@ -1,21 +1,31 @@
+use super::unsafe_code_lints::{
+    count_unsafe_code_lint, parse_rustc_stderr_line, LintedSources,
+    RustcStderrLine,
+};
+
 use cargo::core::compiler::{CompileMode, Executor, Unit};
 use cargo::core::{PackageId, Target};
 use cargo::util::CargoResult;
-use cargo_util::ProcessBuilder;
-use std::collections::HashSet;
+use cargo_geiger_serde::CounterBlock;
+use cargo_util::{ProcessBuilder, ProcessError};
+use geiger::{
+    find_unsafe_in_string, find_unsafe_in_tokens, CfgSet, DangerousApiRules,
+    IncludeTests,
+};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::ffi::OsString;
 use std::fmt;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 /// A cargo Executor to intercept all build tasks and store all ".rs" file
 /// paths for later scanning.
 ///
-/// TODO: This is the place(?) to make rustc perform macro expansion to allow
-/// scanning of the the expanded code. (incl. code generated by build.rs).
-/// Seems to require nightly rust.
+/// It can also make rustc perform macro expansion to scan the expanded code,
+/// including the code generated by build.rs, which requires nightly rust or
+/// `RUSTC_BOOTSTRAP=1` in the environment of cargo-geiger.
 #[derive(Debug)]
 pub struct CustomExecutor {
     /// Current work dir
@@ -23,12 +33,21 @@ pub struct CustomExecutor {
 
     /// Needed since multiple rustc calls can be in flight at the same time.
     pub inner_ctx: Arc<Mutex<CustomExecutorInnerContext>>,
+
+    /// Whether to enable the `unsafe_code` lint of rustc and count its
+    /// diagnostics, instead of forwarding them to cargo.
+    pub lint_unsafe_code: bool,
+
+    /// Whether to rerun rustc to print the macro expanded source of the
+    /// crates compiled, and scan it.
+    pub expand_macros: bool,
 }
 
 #[derive(Debug)]
 enum CustomExecutorError {
     InnerContextMutex(String),
     Io(io::Error, PathBuf),
+    ManifestDirMissing(String),
     OutDirKeyMissing(String),
     OutDirValueMissing(String),
 }
@@ -40,10 +59,10 @@ impl Executor for CustomExecutor {
         &self,
         cmd: &ProcessBuilder,
         _id: PackageId,
-        _target: &Target,
+        target: &Target,
         _mode: CompileMode,
-        _on_stdout_line: &mut dyn FnMut(&str) -> CargoResult<()>,
-        _on_stderr_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+        on_stdout_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+        on_stderr_line: &mut dyn FnMut(&str) -> CargoResult<()>,
     ) -> CargoResult<()> {
         let mut args = cmd.get_args();
         let out_dir_key = OsString::from("--out-dir");
@@ -83,6 +102,35 @@ impl Executor for CustomExecutor {
                 ctx.rs_file_args.insert(path);
             }
             ctx.out_dir_args.insert(out_dir);
+            // Packages with a build script are compiled with the `OUT_DIR`
+            // their build script generated files into
+            if let (Some(out_dir), Some(manifest_dir)) =
+                (cmd.get_env("OUT_DIR"), cmd.get_env("CARGO_MANIFEST_DIR"))
+            {
+                let canonicalize = |raw_path: OsString| {
+                    let raw_path = cwd.join(raw_path);
+                    raw_path
+                        .canonicalize()
+                        .map_err(|e| CustomExecutorError::Io(e, raw_path))
+                };
+                ctx.generated_out_dirs.insert(
+                    canonicalize(out_dir)?,
+                    canonicalize(manifest_dir)?,
+                );
+            }
+        }
+        // Build scripts are left out, like the build script files are left
+        // out of the crate code found by scanning
+        if self.expand_macros && !target.is_custom_build() {
+            self.exec_expanding_macros(cmd, &cwd, target)?;
+        }
+        if self.lint_unsafe_code && !target.is_custom_build() {
+            return self.exec_linting_unsafe_code(
+                cmd,
+                &cwd,
+                on_stdout_line,
+                on_stderr_line,
+            );
         }
         cmd.exec()?;
         Ok(())
@@ -95,6 +143,148 @@ impl Executor for CustomExecutor {
     }
 }
 
+impl CustomExecutor {
+    /// Run rustc with the `unsafe_code` lint forced to warn, and count its
+    /// diagnostics for the package being compiled. Cargo already asks rustc
+    /// for Json diagnostics. Forced warnings are reported even for
+    /// dependencies, which are compiled with their lints capped to `allow`.
+    fn exec_linting_unsafe_code(
+        &self,
+        cmd: &ProcessBuilder,
+        cwd: &Path,
+        on_stdout_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+        on_stderr_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+    ) -> CargoResult<()> {
+        let mut cmd = cmd.clone();
+        if !cmd
+            .get_args()
+            .any(|a| a.to_string_lossy().starts_with("--error-format"))
+        {
+            cmd.arg("--error-format=json");
+        }
+        cmd.arg("--force-warn").arg("unsafe_code");
+
+        let mut counter_block = CounterBlock::default();
+        let mut linted_sources = LintedSources::new(cwd);
+        cmd.exec_with_streaming(
+            on_stdout_line,
+            &mut |line| match parse_rustc_stderr_line(line) {
+                RustcStderrLine::UnsafeCodeLint(span) => {
+                    count_unsafe_code_lint(
+                        &mut counter_block,
+                        &span,
+                        &mut linted_sources,
+                    );
+                    Ok(())
+                }
+                RustcStderrLine::Other => on_stderr_line(line),
+            },
+            false,
+        )?;
+
+        let manifest_dir = manifest_dir(&cmd, cwd)?;
+        let mut ctx = self.inner_ctx.lock().map_err(|e| {
+            CustomExecutorError::InnerContextMutex(e.to_string())
+        })?;
+        *ctx.unsafe_code_lints.entry(manifest_dir).or_default() +=
+            counter_block;
+        Ok(())
+    }
+
+    /// Rerun rustc with `-Zunpretty=expanded` to print the macro expanded
+    /// source of the crate target, and scan it. Only nightly toolchains
+    /// accept the option, unless `RUSTC_BOOTSTRAP=1` is set by the user.
+    /// Targets compiled more than once, like for both the host and the
+    /// target platform, are only expanded once. The first failure of each
+    /// package is kept, and the build goes on without it.
+    fn exec_expanding_macros(
+        &self,
+        cmd: &ProcessBuilder,
+        cwd: &Path,
+        target: &Target,
+    ) -> CargoResult<()> {
+        let manifest_dir = manifest_dir(cmd, cwd)?;
+        let target_name = target.description_named();
+        {
+            let ctx = self.inner_ctx.lock().map_err(|e| {
+                CustomExecutorError::InnerContextMutex(e.to_string())
+            })?;
+            let is_expanded = ctx
+                .expanded
+                .get(&manifest_dir)
+                .is_some_and(|targets| targets.contains_key(&target_name));
+            if is_expanded
+                || ctx.macro_expansion_errors.contains_key(&manifest_dir)
+            {
+                return Ok(());
+            }
+        }
+        let mut expand_cmd = cmd.clone();
+        expand_cmd.arg("-Zunpretty=expanded");
+        let expanded = expand_cmd
+            .exec_with_output()
+            .map_err(|e| process_error_message(&e))
+            .and_then(|output| {
+                String::from_utf8(output.stdout).map_err(|e| e.to_string())
+            })
+            .and_then(|expanded| {
+                // Expanded code has its `#[cfg(..)]` attributes evaluated
+                find_unsafe_in_string(
+                    &expanded,
+                    IncludeTests::No,
+                    &CfgSet::default(),
+                    &DangerousApiRules::new(),
+                )
+                .or_else(|_| find_unsafe_in_tokens(&expanded, IncludeTests::No))
+                .map_err(|e| e.to_string())
+            });
+
+        let mut ctx = self.inner_ctx.lock().map_err(|e| {
+            CustomExecutorError::InnerContextMutex(e.to_string())
+        })?;
+        match expanded {
+            Ok(rs_file_metrics) => {
+                ctx.expanded
+                    .entry(manifest_dir)
+                    .or_default()
+                    .entry(target_name)
+                    .or_insert(rs_file_metrics.counters);
+            }
+            Err(error) => {
+                ctx.macro_expansion_errors
+                    .entry(manifest_dir)
+                    .or_insert(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What rustc wrote to stderr when it failed, which explains why, or the
+/// error itself when it could not be run.
+fn process_error_message(error: &anyhow::Error) -> String {
+    error
+        .downcast_ref::<ProcessError>()
+        .and_then(|process_error| process_error.stderr.as_ref())
+        .map(|stderr| String::from_utf8_lossy(stderr).trim().to_string())
+        .filter(|stderr| !stderr.is_empty())
+        .unwrap_or_else(|| error.to_string())
+}
+
+/// The canonicalized manifest directory of the package compiled by `cmd`.
+fn manifest_dir(
+    cmd: &ProcessBuilder,
+    cwd: &Path,
+) -> Result<PathBuf, CustomExecutorError> {
+    let manifest_dir = cmd.get_env("CARGO_MANIFEST_DIR").ok_or_else(|| {
+        CustomExecutorError::ManifestDirMissing(cmd.to_string())
+    })?;
+    let raw_path = cwd.join(manifest_dir);
+    raw_path
+        .canonicalize()
+        .map_err(|e| CustomExecutorError::Io(e, raw_path))
+}
+
 /// Forward Display to Debug. See the crate root documentation.
 impl fmt::Display for CustomExecutorError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -112,4 +302,22 @@ pub struct CustomExecutorInnerContext {
     /// Investigate if this needs to be intercepted like this or if it can be
     /// looked up in a nicer way.
     pub out_dir_args: HashSet<PathBuf>,
+
+    /// The `OUT_DIR`s of build scripts, mapped to the manifest directory of
+    /// the package compiled with them. Both are canonicalized.
+    pub generated_out_dirs: HashMap<PathBuf, PathBuf>,
+
+    /// The `unsafe` usage reported by the `unsafe_code` lint of rustc, by
+    /// canonicalized manifest directory of the package compiled.
+    pub unsafe_code_lints: HashMap<PathBuf, CounterBlock>,
+
+    /// The `unsafe` usage found in the macro expanded source of the crates
+    /// compiled, by canonicalized manifest directory of their package and
+    /// by crate target.
+    pub expanded: HashMap<PathBuf, HashMap<String, CounterBlock>>,
+
+    /// The first failure to macro expand a crate of each package, by
+    /// canonicalized manifest directory, usually because rustc is not a
+    /// nightly toolchain.
+    pub macro_expansion_errors: HashMap<PathBuf, String>,
 }
@@ -0,0 +1,405 @@
+use cargo_geiger_serde::{CounterBlock, LineColumn, UnsafeKind};
+use geiger::{find_unsafe_in_string, CfgSet, DangerousApiRules, IncludeTests};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The parts of a rustc Json diagnostic needed to count `unsafe_code` lints.
+#[derive(Debug, Deserialize)]
+struct Diagnostic {
+    code: Option<DiagnosticCode>,
+    #[serde(default)]
+    spans: Vec<DiagnosticSpan>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+/// A source location of a rustc diagnostic. Lines and columns are
+/// 1-indexed, columns are in UTF-8 characters and the end is exclusive.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct DiagnosticSpan {
+    pub file_name: PathBuf,
+    pub byte_end: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+    pub is_primary: bool,
+    /// The source lines of the span, with the highlighted columns
+    pub text: Vec<DiagnosticSpanLine>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct DiagnosticSpanLine {
+    pub text: String,
+    pub highlight_start: usize,
+    pub highlight_end: usize,
+}
+
+impl DiagnosticSpan {
+    /// The highlighted source on the first line of the span.
+    fn first_line(&self) -> String {
+        self.text
+            .first()
+            .map(|line| {
+                line.text
+                    .chars()
+                    .skip(line.highlight_start.saturating_sub(1))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A line written by rustc to stderr with `--error-format=json`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum RustcStderrLine {
+    /// A diagnostic of the `unsafe_code` lint, with its primary span.
+    UnsafeCodeLint(DiagnosticSpan),
+
+    /// Other diagnostics and anything that is not a diagnostic.
+    Other,
+}
+
+pub fn parse_rustc_stderr_line(line: &str) -> RustcStderrLine {
+    match serde_json::from_str::<Diagnostic>(line) {
+        Ok(Diagnostic {
+            code: Some(DiagnosticCode { code }),
+            spans,
+        }) if code == "unsafe_code" => spans
+            .into_iter()
+            .find(|span| span.is_primary)
+            .map_or(RustcStderrLine::Other, RustcStderrLine::UnsafeCodeLint),
+        _ => RustcStderrLine::Other,
+    }
+}
+
+/// The source files pointed to by the `unsafe_code` lint diagnostics of a
+/// crate, read once each. Files that cannot be read or parsed, like the
+/// ones of macros expanded from the standard library, are `None`.
+#[derive(Debug)]
+pub struct LintedSources {
+    cwd: PathBuf,
+    files: HashMap<PathBuf, Option<LintedSource>>,
+}
+
+#[derive(Debug)]
+struct LintedSource {
+    text: String,
+    /// The ends of the `unsafe` methods, in the 0-indexed columns of the
+    /// `geiger` crate
+    method_ends: HashSet<LineColumn>,
+}
+
+impl LintedSources {
+    /// The diagnostic file names are relative to the working directory of
+    /// rustc.
+    pub fn new(cwd: &Path) -> Self {
+        LintedSources {
+            cwd: cwd.to_path_buf(),
+            files: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, file_name: &Path) -> Option<&LintedSource> {
+        let cwd = &self.cwd;
+        self.files
+            .entry(file_name.to_path_buf())
+            .or_insert_with(|| {
+                let text = fs::read_to_string(cwd.join(file_name)).ok()?;
+                // Every item rustc lints is compiled, whatever its cfgs
+                let metrics = find_unsafe_in_string(
+                    &text,
+                    IncludeTests::Yes,
+                    &CfgSet::default(),
+                    &DangerousApiRules::new(),
+                )
+                .ok()?;
+                let method_ends = metrics
+                    .unsafe_locations
+                    .into_iter()
+                    .filter(|location| location.kind == UnsafeKind::Method)
+                    .map(|location| location.end)
+                    .collect();
+                Some(LintedSource { text, method_ends })
+            })
+            .as_ref()
+    }
+}
+
+/// Count the `unsafe` usage reported by an `unsafe_code` lint diagnostic,
+/// from the source it points to rather than from the message, which
+/// changes between rustc versions. The lint only reports `unsafe` usage, so
+/// only the unsafe counts are known. Unsafe blocks have their own count, and
+/// functions exported with `no_mangle` or `export_name` count as both unsafe
+/// and exported functions, like the `geiger` crate does.
+pub fn count_unsafe_code_lint(
+    counter_block: &mut CounterBlock,
+    span: &DiagnosticSpan,
+    linted_sources: &mut LintedSources,
+) {
+    let first_line = span.first_line();
+    // The attributes exporting an item are reported, not the item
+    if first_line.starts_with('#') {
+        let exports_function = linted_sources
+            .get(&span.file_name)
+            .and_then(|source| source.text.get(span.byte_end..))
+            .and_then(|rest| {
+                words(rest)
+                    .into_iter()
+                    .find(|(_, word)| *word == "fn" || *word == "static")
+            })
+            .is_some_and(|(_, word)| word == "fn");
+        if exports_function {
+            counter_block.functions.count(true);
+            counter_block.exported_functions.count(true);
+        }
+        return;
+    }
+    let after_unsafe =
+        match words(&first_line).into_iter().find(|(_, w)| *w == "unsafe") {
+            Some((position, _)) => first_line[position + "unsafe".len()..]
+                .trim_start()
+                .to_string(),
+            None => return,
+        };
+    if after_unsafe.starts_with('{') {
+        counter_block.unsafe_blocks += 1;
+        return;
+    }
+    match words(&after_unsafe).first().map(|(_, word)| *word) {
+        Some("impl") => counter_block.item_impls.count(true),
+        Some("trait") | Some("auto") => counter_block.item_traits.count(true),
+        Some("fn") | Some("extern") => {
+            // The span of a function ends with the item, like the location
+            // of a method found by the `geiger` crate
+            let end = LineColumn {
+                line: span.line_end,
+                column: span.column_end.saturating_sub(1),
+            };
+            let is_method = linted_sources
+                .get(&span.file_name)
+                .is_some_and(|source| source.method_ends.contains(&end));
+            if is_method {
+                counter_block.methods.count(true);
+            } else {
+                counter_block.functions.count(true);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The words made of identifier characters in `text`, outside of string
+/// literals, with their byte positions.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (position, c) in text.char_indices() {
+        if in_string {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => in_string = false,
+                _ => escaped = false,
+            }
+            continue;
+        }
+        let is_ident_char = c.is_alphanumeric() || c == '_';
+        match (word_start, is_ident_char) {
+            (None, true) => word_start = Some(position),
+            (Some(start), false) => {
+                words.push((start, &text[start..position]));
+                word_start = None;
+            }
+            _ => {}
+        }
+        in_string = c == '"';
+    }
+    if let Some(start) = word_start {
+        words.push((start, &text[start..]));
+    }
+    words
+}
+
+#[cfg(test)]
+mod unsafe_code_lints_tests {
+    use super::*;
+
+    use cargo_geiger_serde::Count;
+    use rstest::*;
+    use tempfile::tempdir;
+
+    /// The span rustc reports for the first occurrence of `snippet` in
+    /// `source`.
+    fn span(file_name: &str, source: &str, snippet: &str) -> DiagnosticSpan {
+        let byte_start = source.find(snippet).unwrap();
+        let byte_end = byte_start + snippet.len();
+        let line_start = source[..byte_start].matches('\n').count() + 1;
+        let line_end = source[..byte_end].matches('\n').count() + 1;
+        let line_byte_start =
+            source[..byte_start].rfind('\n').map_or(0, |i| i + 1);
+        let line_byte_end = source[..byte_end].rfind('\n').map_or(0, |i| i + 1);
+        let column_start = source[line_byte_start..byte_start].chars().count();
+        DiagnosticSpan {
+            file_name: PathBuf::from(file_name),
+            byte_end,
+            line_end,
+            column_end: source[line_byte_end..byte_end].chars().count() + 1,
+            is_primary: true,
+            text: vec![DiagnosticSpanLine {
+                text: source.lines().nth(line_start - 1).unwrap().to_string(),
+                highlight_start: column_start + 1,
+                highlight_end: column_start + snippet.chars().count() + 1,
+            }],
+        }
+    }
+
+    #[rstest(
+        input_line,
+        expected_rustc_stderr_line,
+        case(
+            r#"{"$message_type":"diagnostic","message":"usage of an `unsafe` block","code":{"code":"unsafe_code","explanation":null},"level":"warning","spans":[{"file_name":"src/lib.rs","byte_start":13,"byte_end":24,"line_start":1,"line_end":1,"column_start":14,"column_end":25,"is_primary":true,"text":[{"text":"pub fn f() { unsafe { } }","highlight_start":14,"highlight_end":25}],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[],"rendered":""}"#,
+            RustcStderrLine::UnsafeCodeLint(DiagnosticSpan {
+                file_name: PathBuf::from("src/lib.rs"),
+                byte_end: 24,
+                line_end: 1,
+                column_end: 25,
+                is_primary: true,
+                text: vec![DiagnosticSpanLine {
+                    text: String::from("pub fn f() { unsafe { } }"),
+                    highlight_start: 14,
+                    highlight_end: 25,
+                }],
+            })
+        ),
+        case(
+            r#"{"$message_type":"diagnostic","message":"usage of an `unsafe` block","code":{"code":"unsafe_code","explanation":null},"level":"warning","spans":[],"children":[],"rendered":""}"#,
+            RustcStderrLine::Other
+        ),
+        case(
+            r#"{"$message_type":"diagnostic","message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[],"children":[],"rendered":""}"#,
+            RustcStderrLine::Other
+        ),
+        case(
+            r#"{"$message_type":"diagnostic","message":"aborting due to previous error","code":null,"level":"error","spans":[],"children":[],"rendered":""}"#,
+            RustcStderrLine::Other
+        ),
+        case(
+            r#"{"$message_type":"artifact","artifact":"lib.rmeta","emit":"metadata"}"#,
+            RustcStderrLine::Other
+        ),
+        case("not json", RustcStderrLine::Other)
+    )]
+    fn parse_rustc_stderr_line_test(
+        input_line: &str,
+        expected_rustc_stderr_line: RustcStderrLine,
+    ) {
+        assert_eq!(
+            parse_rustc_stderr_line(input_line),
+            expected_rustc_stderr_line
+        );
+    }
+
+    #[rstest]
+    fn count_unsafe_code_lint_test() {
+        let source = "\
+/// Docs
+pub unsafe fn f() {}
+pub struct S;
+impl S {
+    /// Docs
+    pub const unsafe fn m() {}
+}
+pub unsafe trait T {
+    unsafe fn t(&self);
+}
+unsafe impl T for S {
+    unsafe fn t(&self) {}
+}
+#[no_mangle]
+#[cfg(feature = \"static\")]
+pub extern \"C\" fn g() {
+    unsafe { f() }
+}
+#[no_mangle]
+pub static X: u8 = 0;
+";
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("lib.rs"), source).unwrap();
+        let mut linted_sources = LintedSources::new(dir.path());
+
+        let mut counter_block = CounterBlock::default();
+        for snippet in [
+            "pub unsafe fn f() {}",
+            "pub const unsafe fn m() {}",
+            "pub unsafe trait T {\n    unsafe fn t(&self);\n}",
+            "unsafe fn t(&self);",
+            "unsafe impl T for S {\n    unsafe fn t(&self) {}\n}",
+            "unsafe fn t(&self) {}",
+            "#[no_mangle]\n#[cfg",
+            "unsafe { f() }",
+            "#[no_mangle]\npub static",
+        ] {
+            let mut span = span("lib.rs", source, snippet);
+            // Keep the attribute only, in the span of the lint
+            if let Some(attribute_end) = snippet.find('\n') {
+                if snippet.starts_with('#') {
+                    span.byte_end -= snippet.len() - attribute_end;
+                }
+            }
+            count_unsafe_code_lint(
+                &mut counter_block,
+                &span,
+                &mut linted_sources,
+            );
+        }
+
+        assert_eq!(
+            counter_block,
+            CounterBlock {
+                functions: Count {
+                    safe: 0,
+                    unsafe_: 2
+                },
+                item_impls: Count {
+                    safe: 0,
+                    unsafe_: 1
+                },
+                item_traits: Count {
+                    safe: 0,
+                    unsafe_: 1
+                },
+                methods: Count {
+                    safe: 0,
+                    unsafe_: 3
+                },
+                exported_functions: Count {
+                    safe: 0,
+                    unsafe_: 1
+                },
+                unsafe_blocks: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[rstest]
+    fn count_unsafe_code_lint_test_unreadable_source() {
+        let source = "impl S {\n    unsafe fn m() {}\n}\n";
+        let mut linted_sources = LintedSources::new(Path::new("/nonexistent"));
+        let mut counter_block = CounterBlock::default();
+
+        count_unsafe_code_lint(
+            &mut counter_block,
+            &span("lib.rs", source, "unsafe fn m() {}"),
+            &mut linted_sources,
+        );
+
+        assert_eq!(counter_block.functions.unsafe_, 1);
+        assert_eq!(counter_block.methods.unsafe_, 0);
+    }
+}
@@ -130,6 +130,7 @@ mod tree_tests {
         PrintConfig {
             all: false,
             direction: EdgeDirection::Outgoing,
+            extra_columns: false,
             prefix,
             format: pattern,
             allow_partial_results: false,
@@ -165,6 +165,7 @@ mod dependency_node_tests {
         let graph = Graph {
             graph: inner_graph,
             nodes,
+            cfgs: None,
         };
 
         let dependency_type_nodes_hashmap =
@@ -216,6 +217,7 @@ mod dependency_node_tests {
             all: false,
             allow_partial_results: false,
             direction: edge_direction,
+            extra_columns: false,
             format: Pattern::new(vec![]),
             include_tests: IncludeTests::Yes,
             prefix: Prefix::Depth,
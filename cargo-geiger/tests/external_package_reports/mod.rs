@@ -2,8 +2,8 @@ use crate::context::Context;
 use crate::integration_test::IntegrationTest;
 use crate::report::{merge_test_reports, single_entry_safety_report, to_set};
 use cargo_geiger_serde::{
-    Count, CounterBlock, PackageId, PackageInfo, ReportEntry, SafetyReport,
-    Source, UnsafeInfo,
+    Count, CounterBlock, LintStatus, PackageId, PackageInfo, ReportEntry,
+    SafetyReport, Source, UnsafeInfo,
 };
 use semver::Version;
 use url::Url;
@@ -59,11 +59,11 @@ pub fn either_safety_report() -> SafetyReport {
         unsafety: UnsafeInfo {
             used: CounterBlock {
                 functions: Count {
-                    safe: 6,
+                    safe: 5,
                     unsafe_: 0,
                 },
                 exprs: Count {
-                    safe: 102,
+                    safe: 99,
                     unsafe_: 0,
                 },
                 item_impls: Count {
@@ -76,6 +76,17 @@ pub fn either_safety_report() -> SafetyReport {
                 },
                 ..Default::default()
             },
+            unused: CounterBlock {
+                functions: Count {
+                    safe: 1,
+                    unsafe_: 0,
+                },
+                exprs: Count {
+                    safe: 3,
+                    unsafe_: 0,
+                },
+                ..Default::default()
+            },
             ..Default::default()
         },
     };
@@ -148,9 +159,10 @@ pub fn itertools_safety_report() -> SafetyReport {
                     unsafe_: 0,
                 },
                 methods: Count {
-                    safe: 180,
+                    safe: 246,
                     unsafe_: 0,
                 },
+                ..Default::default()
             },
             unused: CounterBlock {
                 functions: Count {
@@ -170,9 +182,14 @@ pub fn itertools_safety_report() -> SafetyReport {
                     unsafe_: 1,
                 },
                 methods: Count {
-                    safe: 29,
-                    unsafe_: 3,
+                    safe: 32,
+                    unsafe_: 4,
+                },
+                statics: Count {
+                    safe: 1,
+                    unsafe_: 0,
                 },
+                ..Default::default()
             },
             ..Default::default()
         },
@@ -248,6 +265,8 @@ pub fn generational_arena_safety_report() -> SafetyReport {
                 ..Default::default()
             },
             forbids_unsafe: true,
+            lint_status: LintStatus::Forbid,
+            ..Default::default()
         },
     };
     let mut report = single_entry_safety_report(entry);
@@ -283,6 +302,10 @@ pub fn idna_safety_report() -> SafetyReport {
                     safe: 13596,
                     unsafe_: 1,
                 },
+                statics: Count {
+                    safe: 13,
+                    unsafe_: 0,
+                },
                 ..Default::default()
             },
             unused: CounterBlock {
@@ -340,21 +363,26 @@ pub fn smallvec_safety_report() -> SafetyReport {
                     unsafe_: 2,
                 },
                 exprs: Count {
-                    safe: 291,
-                    unsafe_: 354,
+                    safe: 261,
+                    unsafe_: 334,
                 },
                 item_impls: Count {
-                    safe: 48,
-                    unsafe_: 4,
+                    safe: 42,
+                    unsafe_: 3,
                 },
                 item_traits: Count {
-                    safe: 3,
+                    safe: 2,
                     unsafe_: 1,
                 },
                 methods: Count {
-                    safe: 92,
-                    unsafe_: 13,
+                    safe: 87,
+                    unsafe_: 8,
                 },
+                send_sync_impls: Count {
+                    safe: 0,
+                    unsafe_: 3,
+                },
+                ..Default::default()
             },
             unused: CounterBlock {
                 functions: Count {
@@ -362,21 +390,22 @@ pub fn smallvec_safety_report() -> SafetyReport {
                     unsafe_: 0,
                 },
                 exprs: Count {
-                    safe: 126,
-                    unsafe_: 0,
+                    safe: 156,
+                    unsafe_: 20,
                 },
                 item_impls: Count {
-                    safe: 2,
-                    unsafe_: 0,
+                    safe: 8,
+                    unsafe_: 1,
                 },
                 item_traits: Count {
-                    safe: 1,
+                    safe: 2,
                     unsafe_: 0,
                 },
                 methods: Count {
-                    safe: 14,
-                    unsafe_: 0,
+                    safe: 32,
+                    unsafe_: 5,
                 },
+                ..Default::default()
             },
             ..Default::default()
         },
@@ -405,7 +434,7 @@ pub fn unicode_bidi_safety_report() -> SafetyReport {
                     unsafe_: 0,
                 },
                 exprs: Count {
-                    safe: 2119,
+                    safe: 2107,
                     unsafe_: 0,
                 },
                 item_impls: Count {
@@ -419,6 +448,7 @@ pub fn unicode_bidi_safety_report() -> SafetyReport {
                 ..Default::default()
             },
             forbids_unsafe: true,
+            lint_status: LintStatus::Forbid,
             ..Default::default()
         },
     };
@@ -460,9 +490,10 @@ pub(super) fn unicode_normalization_safety_report() -> SafetyReport {
                     unsafe_: 0,
                 },
                 methods: Count {
-                    safe: 21,
+                    safe: 26,
                     unsafe_: 0,
                 },
+                ..Default::default()
             },
             unused: CounterBlock {
                 functions: Count {
@@ -42,6 +42,7 @@ pub fn to_quick_report(report: SafetyReport) -> QuickSafetyReport {
             let quick_entry = QuickReportEntry {
                 package: entry.package,
                 forbids_unsafe: entry.unsafety.forbids_unsafe,
+                lint_status: entry.unsafety.lint_status,
             };
             (id, quick_entry)
         })
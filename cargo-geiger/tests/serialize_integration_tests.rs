@@ -13,8 +13,8 @@ use self::integration_test::IntegrationTest;
 use self::report::{merge_test_reports, single_entry_safety_report, to_set};
 
 use cargo_geiger_serde::{
-    Count, CounterBlock, PackageInfo, ReportEntry, SafetyReport, Source,
-    UnsafeInfo,
+    Count, CounterBlock, LintStatus, PackageInfo, ReportEntry, SafetyReport,
+    Source, UnsafeInfo,
 };
 use rstest::rstest;
 use std::path::PathBuf;
@@ -145,6 +145,11 @@ impl IntegrationTest for Test2 {
                         safe: 0,
                         unsafe_: 4,
                     },
+                    statics: Count {
+                        safe: 0,
+                        unsafe_: 1,
+                    },
+                    static_mut_accesses: 2,
                     ..Default::default()
                 },
                 ..Default::default()
@@ -289,6 +294,7 @@ impl IntegrationTest for Test6 {
                     ..Default::default()
                 },
                 forbids_unsafe: true,
+                lint_status: LintStatus::Forbid,
                 ..Default::default()
             },
         }
@@ -331,6 +337,7 @@ impl IntegrationTest for Test7 {
                     ..Default::default()
                 },
                 forbids_unsafe: true,
+                lint_status: LintStatus::Forbid,
                 ..Default::default()
             },
         }
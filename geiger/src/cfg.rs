@@ -0,0 +1,254 @@
+use super::meta_list_nested;
+
+use std::collections::HashSet;
+use syn::{Attribute, Expr, ExprLit, Lit, Meta};
+
+/// Names of the configuration options that `rustc --print cfg` describes
+/// completely for a target. An option with one of these names that is not
+/// enabled in a set built from the target cfgs is known to be disabled.
+const TARGET_CFG_NAMES: [&str; 14] = [
+    "debug_assertions",
+    "panic",
+    "target_abi",
+    "target_arch",
+    "target_endian",
+    "target_env",
+    "target_family",
+    "target_feature",
+    "target_has_atomic",
+    "target_os",
+    "target_pointer_width",
+    "target_vendor",
+    "unix",
+    "windows",
+];
+
+/// A set of enabled configuration options, used to evaluate `#[cfg(..)]`
+/// attributes. Options whose name is not known to the set evaluate to
+/// neither true nor false, and the code depending on them is treated as
+/// compiled. An empty set therefore treats all code as compiled.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CfgSet {
+    enabled: HashSet<(String, Option<String>)>,
+    known_names: HashSet<String>,
+}
+
+impl CfgSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A set of the cfgs reported by rustc for a target, given as names like
+    /// `unix` and key-value pairs like `target_os = "linux"`. Target options
+    /// missing from `cfgs` are known to be disabled.
+    pub fn from_target_cfgs<I>(cfgs: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Option<String>)>,
+    {
+        let mut cfg_set = Self::new();
+        for name in TARGET_CFG_NAMES.iter() {
+            cfg_set.insert_known_name(name);
+        }
+        for (name, value) in cfgs {
+            cfg_set.insert(name, value);
+        }
+        cfg_set
+    }
+
+    /// Enables an option, either a name like `unix` when `value` is `None`,
+    /// or a key-value pair like `target_os = "linux"`. The name becomes known
+    /// to the set.
+    pub fn insert(&mut self, name: String, value: Option<String>) {
+        self.known_names.insert(name.clone());
+        self.enabled.insert((name, value));
+    }
+
+    /// Makes a name known to the set, so that the options with this name
+    /// that are not enabled evaluate to false.
+    pub fn insert_known_name(&mut self, name: &str) {
+        self.known_names.insert(name.to_string());
+    }
+
+    /// Enables the features of a package, as resolved by cargo. Features
+    /// missing from `features` are known to be disabled.
+    pub fn insert_features<I>(&mut self, features: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.insert_known_name("feature");
+        for feature in features {
+            self.insert(String::from("feature"), Some(feature));
+        }
+    }
+
+    /// Evaluates a cfg predicate such as `all(unix, not(target_os = "macos"))`.
+    /// Returns `None` when the result depends on options whose name is not
+    /// known to the set.
+    pub fn evaluate(&self, predicate: &Meta) -> Option<bool> {
+        match predicate {
+            Meta::Path(path) => {
+                self.evaluate_option(&path.get_ident()?.to_string(), None)
+            }
+            Meta::NameValue(name_value) => {
+                let value = match &name_value.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(value),
+                        ..
+                    }) => value.value(),
+                    _ => return None,
+                };
+                self.evaluate_option(
+                    &name_value.path.get_ident()?.to_string(),
+                    Some(value),
+                )
+            }
+            Meta::List(list) => {
+                let nested = meta_list_nested(list);
+                let mut operands =
+                    nested.iter().map(|operand| self.evaluate(operand));
+                if list.path.is_ident("all") {
+                    all(operands)
+                } else if list.path.is_ident("any") {
+                    any(operands)
+                } else if list.path.is_ident("not") && nested.len() == 1 {
+                    operands.next().unwrap().map(|result| !result)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Evaluates all `#[cfg(..)]` attributes in `attrs`, which must all hold
+    /// for the code to be compiled.
+    pub fn evaluate_attributes(&self, attrs: &[Attribute]) -> Option<bool> {
+        all(cfg_predicates(attrs).map(|predicate| self.evaluate(&predicate)))
+    }
+
+    fn evaluate_option(
+        &self,
+        name: &str,
+        value: Option<String>,
+    ) -> Option<bool> {
+        if !self.known_names.contains(name) {
+            return None;
+        }
+        Some(self.enabled.contains(&(name.to_string(), value)))
+    }
+}
+
+/// The predicates of the `#[cfg(..)]` attributes in `attrs`.
+fn cfg_predicates(attrs: &[Attribute]) -> impl Iterator<Item = Meta> + '_ {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .filter_map(|attr| attr.parse_args::<Meta>().ok())
+}
+
+/// False if any operand is false, true if all operands are true.
+fn all<I>(operands: I) -> Option<bool>
+where
+    I: Iterator<Item = Option<bool>>,
+{
+    let mut result = Some(true);
+    for operand in operands {
+        match operand {
+            Some(false) => return Some(false),
+            None => result = None,
+            Some(true) => {}
+        }
+    }
+    result
+}
+
+/// True if any operand is true, false if all operands are false.
+fn any<I>(operands: I) -> Option<bool>
+where
+    I: Iterator<Item = Option<bool>>,
+{
+    let mut result = Some(false);
+    for operand in operands {
+        match operand {
+            Some(true) => return Some(true),
+            None => result = None,
+            Some(false) => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod cfg_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(
+        input_predicate,
+        expected_result,
+        case("unix", Some(true)),
+        case("windows", Some(false)),
+        case("target_os = \"linux\"", Some(true)),
+        case("target_os = \"windows\"", Some(false)),
+        case("all(unix, target_pointer_width = \"64\")", Some(true)),
+        case("all(unix, not(target_os = \"linux\"))", Some(false)),
+        case("any(windows, target_os = \"macos\")", Some(false)),
+        case("any(windows, unix)", Some(true)),
+        case("feature = \"std\"", None),
+        case("any(feature = \"std\", unix)", Some(true)),
+        case("all(feature = \"std\", unix)", None),
+        case("all(feature = \"std\", windows)", Some(false)),
+        case("not(test)", None),
+        case("all()", Some(true)),
+        case("any()", Some(false))
+    )]
+    fn evaluate_test(input_predicate: &str, expected_result: Option<bool>) {
+        let cfg_set = CfgSet::from_target_cfgs(vec![
+            (String::from("unix"), None),
+            (String::from("target_os"), Some(String::from("linux"))),
+            (
+                String::from("target_pointer_width"),
+                Some(String::from("64")),
+            ),
+        ]);
+        let predicate = syn::parse_str::<Meta>(input_predicate).unwrap();
+
+        assert_eq!(cfg_set.evaluate(&predicate), expected_result);
+    }
+
+    #[rstest]
+    fn evaluate_attributes_test() {
+        let mut cfg_set = CfgSet::new();
+        cfg_set.insert_known_name("test");
+        let item = syn::parse_str::<syn::ItemFn>(
+            "#[cfg(not(test))]\n#[cfg(unix)]\n#[inline]\nfn f() {}",
+        )
+        .unwrap();
+
+        assert_eq!(cfg_set.evaluate_attributes(&item.attrs), None);
+
+        cfg_set.insert(String::from("test"), None);
+
+        assert_eq!(cfg_set.evaluate_attributes(&item.attrs), Some(false));
+        assert_eq!(cfg_set.evaluate_attributes(&[]), Some(true));
+    }
+
+    #[rstest(
+        input_predicate,
+        expected_result,
+        case("feature = \"std\"", Some(true)),
+        case("feature = \"nightly\"", Some(false)),
+        case("all(feature = \"std\", not(feature = \"simd\"))", Some(true)),
+        case("all(feature = \"std\", unix)", None)
+    )]
+    fn insert_features_test(
+        input_predicate: &str,
+        expected_result: Option<bool>,
+    ) {
+        let mut cfg_set = CfgSet::new();
+        cfg_set.insert_features(vec![String::from("std")]);
+        let predicate = syn::parse_str::<Meta>(input_predicate).unwrap();
+
+        assert_eq!(cfg_set.evaluate(&predicate), expected_result);
+    }
+}
@@ -0,0 +1,207 @@
+use syn::{ExprPath, Ident, Type};
+
+/// Known-dangerous functions and methods flagged by the built-in rule set.
+const BUILTIN_RULES: [&str; 17] = [
+    "Box::from_raw",
+    "MaybeUninit.assume_init",
+    "String::from_utf8_unchecked",
+    "Vec::from_raw_parts",
+    "Vec.set_len",
+    "hint::unreachable_unchecked",
+    "mem::transmute",
+    "mem::transmute_copy",
+    "mem::uninitialized",
+    "mem::zeroed",
+    "ptr::read",
+    "ptr::write",
+    "slice.get_unchecked",
+    "slice.get_unchecked_mut",
+    "slice::from_raw_parts",
+    "slice::from_raw_parts_mut",
+    "str::from_utf8_unchecked",
+];
+
+/// A set of known-dangerous APIs, given as paths of functions such as
+/// `mem::transmute`, or as methods such as `Vec.set_len`, written with the
+/// name of the type of their receiver as a hint. Types are not resolved, so
+/// calls are matched syntactically: a call by path matches a rule when the
+/// rule is a suffix of the path as written, such as `Vec::set_len(&mut v, 0)`
+/// or `<[u8]>::get_unchecked(s, 0)`, and a method call matches a method rule
+/// of the same name, whatever its receiver. Functions never match method
+/// calls, so `ptr::read` does not match `file.read(&mut buf)`. An empty set,
+/// the default, matches nothing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DangerousApiRules {
+    rules: Vec<DangerousApiRule>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct DangerousApiRule {
+    rule: String,
+    /// The path of a function, or the receiver type and name of a method
+    segments: Vec<String>,
+    is_method: bool,
+}
+
+impl DangerousApiRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rule set shipped with geiger.
+    pub fn builtin() -> Self {
+        let mut rules = Self::new();
+        for rule in BUILTIN_RULES.iter() {
+            rules.insert(rule);
+        }
+        rules
+    }
+
+    /// Adds a rule, unless it is already part of the set.
+    pub fn insert(&mut self, rule: &str) {
+        let rule = rule.trim();
+        if rule.is_empty() || self.rules.iter().any(|r| r.rule == rule) {
+            return;
+        }
+        let (path, method) = match rule.rsplit_once('.') {
+            Some((receiver_type, method)) => (receiver_type, Some(method)),
+            None => (rule, None),
+        };
+        let segments = path
+            .split("::")
+            .chain(method)
+            .map(|segment| segment.trim().to_string())
+            .collect();
+        self.rules.push(DangerousApiRule {
+            rule: rule.to_string(),
+            segments,
+            is_method: method.is_some(),
+        });
+    }
+
+    pub fn remove(&mut self, rule: &str) {
+        self.rules.retain(|r| r.rule != rule.trim());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first rule matching a call by path such as
+    /// `std::mem::transmute(x)`. The self type of a qualified path such as
+    /// `<[u8]>::get_unchecked` is named like the receiver types of the rules,
+    /// `slice` for slices.
+    pub fn match_path(&self, expr_path: &ExprPath) -> Option<&str> {
+        let self_type = expr_path
+            .qself
+            .as_ref()
+            .filter(|qself| qself.position == 0)
+            .map(|qself| receiver_type_name(&qself.ty));
+        let segments = self_type
+            .into_iter()
+            .chain(
+                expr_path
+                    .path
+                    .segments
+                    .iter()
+                    .map(|segment| segment.ident.to_string()),
+            )
+            .collect::<Vec<_>>();
+        self.rules
+            .iter()
+            .find(|r| segments.ends_with(&r.segments))
+            .map(|r| r.rule.as_str())
+    }
+
+    /// The first method rule matching a method call such as
+    /// `v.get_unchecked(0)`.
+    pub fn match_method(&self, method: &Ident) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|r| {
+                r.is_method
+                    && r.segments.last().is_some_and(|name| method == name)
+            })
+            .map(|r| r.rule.as_str())
+    }
+}
+
+fn receiver_type_name(ty: &Type) -> String {
+    match ty {
+        Type::Array(_) | Type::Slice(_) => String::from("slice"),
+        Type::Group(type_group) => receiver_type_name(&type_group.elem),
+        Type::Paren(type_paren) => receiver_type_name(&type_paren.elem),
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        Type::Reference(type_reference) => {
+            receiver_type_name(&type_reference.elem)
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod dangerous_api_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(
+        input_path,
+        expected_rule,
+        case("std::mem::transmute", Some("mem::transmute")),
+        case("mem::transmute", Some("mem::transmute")),
+        case("transmute", None),
+        case("core::slice::from_raw_parts", Some("slice::from_raw_parts")),
+        case("Vec::from_raw_parts", Some("Vec::from_raw_parts")),
+        case("String::from_raw_parts", None),
+        case("<[u8]>::get_unchecked", Some("slice.get_unchecked")),
+        case("Vec::set_len", Some("Vec.set_len")),
+        case("get_unchecked", None),
+        case("std::fs::read", None)
+    )]
+    fn match_path_test(input_path: &str, expected_rule: Option<&str>) {
+        let rules = DangerousApiRules::builtin();
+        let expr_path = syn::parse_str::<ExprPath>(input_path).unwrap();
+
+        assert_eq!(rules.match_path(&expr_path), expected_rule);
+    }
+
+    #[rstest(
+        input_method,
+        expected_rule,
+        case("assume_init", Some("MaybeUninit.assume_init")),
+        case("set_len", Some("Vec.set_len")),
+        case("get_unchecked", Some("slice.get_unchecked")),
+        case("get", None),
+        case("read", None),
+        case("write", None)
+    )]
+    fn match_method_test(input_method: &str, expected_rule: Option<&str>) {
+        let rules = DangerousApiRules::builtin();
+        let method = syn::parse_str::<Ident>(input_method).unwrap();
+
+        assert_eq!(rules.match_method(&method), expected_rule);
+    }
+
+    #[rstest]
+    fn insert_and_remove_test() {
+        let mut rules = DangerousApiRules::new();
+        assert!(rules.is_empty());
+
+        rules.insert("ptr::read");
+        rules.insert(" ptr::read ");
+        rules.insert("Rc::from_raw");
+        rules.remove("ptr::read");
+
+        let rc_from_raw =
+            syn::parse_str::<ExprPath>("std::rc::Rc::from_raw").unwrap();
+        let ptr_read = syn::parse_str::<ExprPath>("ptr::read").unwrap();
+        assert_eq!(rules.match_path(&rc_from_raw), Some("Rc::from_raw"));
+        assert_eq!(rules.match_path(&ptr_read), None);
+    }
+}
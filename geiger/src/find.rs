@@ -1,4 +1,7 @@
-use super::{IncludeTests, RsFileMetrics, ScanFileError};
+use super::{
+    find_unsafe_in_tokens, CfgSet, DangerousApiRules, IncludeTests,
+    MetricPlugin, RsFileMetrics, ScanFileError,
+};
 
 use crate::geiger_syn_visitor::GeigerSynVisitor;
 
@@ -6,10 +9,39 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
-/// Scan a single file for `unsafe` usage.
+/// Scan a single file for `unsafe` usage. Code excluded by `#[cfg(..)]`
+/// attributes that evaluate to false for `cfg_set` is counted separately in
+/// the inactive counters. Calls to the APIs of `dangerous_api_rules` are
+/// counted by rule. A file that can not be parsed is scanned token by token
+/// instead, and its metrics are marked as approximate, so that it does not
+/// look free of `unsafe`.
 pub fn find_unsafe_in_file(
     path: &Path,
     include_tests: IncludeTests,
+    cfg_set: &CfgSet,
+    dangerous_api_rules: &DangerousApiRules,
+) -> Result<RsFileMetrics, ScanFileError> {
+    scan_file(path, include_tests, cfg_set, dangerous_api_rules, &[])
+}
+
+/// Scan a string of Rust source code for `unsafe` usage. The file paths of the
+/// returned unsafe locations are left empty.
+pub fn find_unsafe_in_string(
+    src: &str,
+    include_tests: IncludeTests,
+    cfg_set: &CfgSet,
+    dangerous_api_rules: &DangerousApiRules,
+) -> Result<RsFileMetrics, syn::Error> {
+    scan_string(src, include_tests, cfg_set, dangerous_api_rules, &[])
+}
+
+/// `find_unsafe_in_file`, also counting the metrics of `plugins`.
+pub(crate) fn scan_file(
+    path: &Path,
+    include_tests: IncludeTests,
+    cfg_set: &CfgSet,
+    dangerous_api_rules: &DangerousApiRules,
+    plugins: &[Box<dyn MetricPlugin>],
 ) -> Result<RsFileMetrics, ScanFileError> {
     let mut file = File::open(path)
         .map_err(|e| ScanFileError::Io(e, path.to_path_buf()))?;
@@ -18,18 +50,43 @@ pub fn find_unsafe_in_file(
         .map_err(|e| ScanFileError::Io(e, path.to_path_buf()))?;
     let src = String::from_utf8(src)
         .map_err(|e| ScanFileError::Utf8(e, path.to_path_buf()))?;
-    find_unsafe_in_string(&src, include_tests)
-        .map_err(|e| ScanFileError::Syn(e, path.to_path_buf()))
+    let mut metrics =
+        scan_string(&src, include_tests, cfg_set, dangerous_api_rules, plugins)
+            .or_else(|e| {
+                find_unsafe_in_tokens(&src, include_tests)
+                    .map(|metrics| RsFileMetrics {
+                        parse_error: Some(e.to_string()),
+                        ..metrics
+                    })
+                    .map_err(|_| ScanFileError::Syn(e, path.to_path_buf()))
+            })?;
+    for location in &mut metrics.unsafe_locations {
+        location.file = path.to_path_buf();
+    }
+    for function in &mut metrics.functions {
+        function.file = path.to_path_buf();
+    }
+    Ok(metrics)
 }
 
-pub fn find_unsafe_in_string(
+/// `find_unsafe_in_string`, also counting the metrics of `plugins`.
+pub(crate) fn scan_string(
     src: &str,
     include_tests: IncludeTests,
+    cfg_set: &CfgSet,
+    dangerous_api_rules: &DangerousApiRules,
+    plugins: &[Box<dyn MetricPlugin>],
 ) -> Result<RsFileMetrics, syn::Error> {
     use syn::visit::Visit;
     let syntax = syn::parse_file(src)?;
-    let mut vis = GeigerSynVisitor::new(include_tests);
+    let mut vis =
+        GeigerSynVisitor::new(src, include_tests, cfg_set, dangerous_api_rules);
     vis.visit_file(&syntax);
+    for plugin in plugins {
+        vis.metrics
+            .plugin_metrics
+            .insert(plugin.name().to_string(), plugin.count(&syntax));
+    }
     Ok(vis.metrics)
 }
 
@@ -37,8 +94,13 @@ pub fn find_unsafe_in_string(
 mod find_tests {
     use super::*;
 
-    use cargo_geiger_serde::{Count, CounterBlock};
+    use cargo_geiger_serde::{
+        Count, CounterBlock, FunctionMetrics, LineColumn, LintStatus,
+        SafetyCommentCoverage, UnsafeKind, UnsafeLocation,
+        UnsafeOperationKinds, UnsafeScopeKinds,
+    };
     use rstest::*;
+    use std::collections::BTreeMap;
     use std::io::Write;
     use tempfile::tempdir;
 
@@ -89,8 +151,8 @@ mod tests {
                     unsafe_: 3
                 },
                 exprs: Count {
-                    safe: 4,
-                    unsafe_: 5
+                    safe: 6,
+                    unsafe_: 2
                 },
                 item_impls: Count {
                     safe: 0,
@@ -103,9 +165,58 @@ mod tests {
                 methods: Count {
                     safe: 0,
                     unsafe_: 0
-                }
+                },
+                unsafe_in_macros: 0,
+                foreign_functions: Count {
+                    safe: 0,
+                    unsafe_: 0
+                },
+                foreign_statics: Count {
+                    safe: 0,
+                    unsafe_: 0
+                },
+                exported_functions: Count {
+                    safe: 0,
+                    unsafe_: 0
+                },
+                statics: Count {
+                    safe: 0,
+                    unsafe_: 0
+                },
+                static_mut_accesses: 0,
+                send_sync_impls: Count {
+                    safe: 0,
+                    unsafe_: 0
+                },
+                marker_trait_impls: Count {
+                    safe: 0,
+                    unsafe_: 0
+                },
+                unsafe_blocks: 0,
+                unsafe_operations: Some(UnsafeOperationKinds {
+                    unsafe_fn_calls: 1,
+                    ..Default::default()
+                }),
+                unsafe_scopes: Some(UnsafeScopeKinds {
+                    implicit_exprs: 1,
+                    explicit_exprs: 1,
+                    redundant_blocks: 0,
+                }),
+                dangerous_api_calls: BTreeMap::new(),
             },
-            forbids_unsafe: false
+            inactive_counters: CounterBlock::default(),
+            forbids_unsafe: false,
+            lint_status: LintStatus::Allow,
+            allows_unsafe_locally: false,
+            unsafe_locations: file_content_unsafe_locations(IncludeTests::Yes),
+            functions: file_content_functions(IncludeTests::Yes),
+            safety_comments: SafetyCommentCoverage {
+                documented: 0,
+                total: 2,
+            },
+            approximate: false,
+            parse_error: None,
+            plugin_metrics: BTreeMap::new(),
         }
         ),
         case(
@@ -117,8 +228,8 @@ mod tests {
                         unsafe_: 3
                     },
                     exprs: Count {
-                        safe: 4,
-                        unsafe_: 4
+                        safe: 6,
+                        unsafe_: 2
                     },
                     item_impls: Count {
                         safe: 0,
@@ -131,9 +242,58 @@ mod tests {
                     methods: Count {
                         safe: 0,
                         unsafe_: 0
-                    }
+                    },
+                    unsafe_in_macros: 0,
+                    foreign_functions: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    foreign_statics: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    exported_functions: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    statics: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    static_mut_accesses: 0,
+                    send_sync_impls: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    marker_trait_impls: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    unsafe_blocks: 0,
+                    unsafe_operations: Some(UnsafeOperationKinds {
+                        unsafe_fn_calls: 1,
+                        ..Default::default()
+                    }),
+                    unsafe_scopes: Some(UnsafeScopeKinds {
+                        implicit_exprs: 1,
+                        explicit_exprs: 1,
+                        redundant_blocks: 0,
+                    }),
+                    dangerous_api_calls: BTreeMap::new(),
+                },
+                inactive_counters: CounterBlock::default(),
+                forbids_unsafe: false,
+                lint_status: LintStatus::Allow,
+                allows_unsafe_locally: false,
+                unsafe_locations: file_content_unsafe_locations(IncludeTests::No),
+                functions: file_content_functions(IncludeTests::No),
+                safety_comments: SafetyCommentCoverage {
+                    documented: 0,
+                    total: 1,
                 },
-                forbids_unsafe: false
+                approximate: false,
+                parse_error: None,
+                plugin_metrics: BTreeMap::new(),
             }
         )
     )]
@@ -147,16 +307,95 @@ mod tests {
 
         writeln!(file, "{}", FILE_CONTENT_STRING).unwrap();
 
-        let unsafe_in_file_result =
-            find_unsafe_in_file(&lib_file_path, input_include_tests);
+        let unsafe_in_file_result = find_unsafe_in_file(
+            &lib_file_path,
+            input_include_tests,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        );
 
         assert!(unsafe_in_file_result.is_ok());
 
-        let unsafe_in_file = unsafe_in_file_result.unwrap();
+        let mut unsafe_in_file = unsafe_in_file_result.unwrap();
 
+        for location in &mut unsafe_in_file.unsafe_locations {
+            assert_eq!(location.file, lib_file_path);
+            location.file = Default::default();
+        }
+        for function in &mut unsafe_in_file.functions {
+            assert_eq!(function.file, lib_file_path);
+            function.file = Default::default();
+        }
         assert_eq!(unsafe_in_file, expected_rs_file_metrics);
     }
 
+    #[rstest]
+    fn find_unsafe_in_file_test_unparsable_file() {
+        let temp_dir = tempdir().unwrap();
+        let lib_file_path = temp_dir.path().join("lib.rs");
+        let mut file = File::create(lib_file_path.clone()).unwrap();
+
+        writeln!(file, "fn f() -> u8 {{\n    unsafe {{ 1 }} +\n}}").unwrap();
+
+        let unsafe_in_file = find_unsafe_in_file(
+            &lib_file_path,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert!(unsafe_in_file.approximate);
+        assert!(unsafe_in_file.parse_error.is_some());
+        assert_eq!(
+            unsafe_in_file.counters.functions,
+            Count {
+                safe: 1,
+                unsafe_: 0
+            }
+        );
+        assert_eq!(
+            unsafe_in_file.counters.exprs,
+            Count {
+                safe: 0,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            unsafe_in_file.unsafe_locations,
+            vec![UnsafeLocation {
+                file: lib_file_path,
+                start: LineColumn { line: 2, column: 4 },
+                end: LineColumn {
+                    line: 2,
+                    column: 16
+                },
+                kind: UnsafeKind::Block,
+            }]
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_file_test_untokenizable_file() {
+        let temp_dir = tempdir().unwrap();
+        let lib_file_path = temp_dir.path().join("lib.rs");
+        let mut file = File::create(lib_file_path.clone()).unwrap();
+
+        writeln!(file, "fn f() {{").unwrap();
+
+        let unsafe_in_file_result = find_unsafe_in_file(
+            &lib_file_path,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        );
+
+        assert!(matches!(
+            unsafe_in_file_result,
+            Err(ScanFileError::Syn(_, path)) if path == lib_file_path
+        ));
+    }
+
     #[rstest(
         input_include_tests,
         expected_rs_file_metrics,
@@ -169,8 +408,8 @@ mod tests {
                         unsafe_: 3
                     },
                     exprs: Count {
-                        safe: 4,
-                        unsafe_: 5
+                        safe: 6,
+                        unsafe_: 2
                     },
                     item_impls: Count {
                         safe: 0,
@@ -183,9 +422,58 @@ mod tests {
                     methods: Count {
                         safe: 0,
                         unsafe_: 0
-                    }
+                    },
+                    unsafe_in_macros: 0,
+                    foreign_functions: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    foreign_statics: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    exported_functions: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    statics: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    static_mut_accesses: 0,
+                    send_sync_impls: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    marker_trait_impls: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    unsafe_blocks: 0,
+                    unsafe_operations: Some(UnsafeOperationKinds {
+                        unsafe_fn_calls: 1,
+                        ..Default::default()
+                    }),
+                    unsafe_scopes: Some(UnsafeScopeKinds {
+                        implicit_exprs: 1,
+                        explicit_exprs: 1,
+                        redundant_blocks: 0,
+                    }),
+                    dangerous_api_calls: BTreeMap::new(),
+                },
+                inactive_counters: CounterBlock::default(),
+                forbids_unsafe: false,
+                lint_status: LintStatus::Allow,
+                allows_unsafe_locally: false,
+                unsafe_locations: file_content_unsafe_locations(IncludeTests::Yes),
+                functions: file_content_functions(IncludeTests::Yes),
+                safety_comments: SafetyCommentCoverage {
+                    documented: 0,
+                    total: 2,
                 },
-                forbids_unsafe: false
+                approximate: false,
+                parse_error: None,
+                plugin_metrics: BTreeMap::new(),
             }
         ),
         case(
@@ -197,8 +485,8 @@ mod tests {
                         unsafe_: 3
                     },
                     exprs: Count {
-                        safe: 4,
-                        unsafe_: 4
+                        safe: 6,
+                        unsafe_: 2
                     },
                     item_impls: Count {
                         safe: 0,
@@ -211,9 +499,58 @@ mod tests {
                     methods: Count {
                         safe: 0,
                         unsafe_: 0
-                    }
+                    },
+                    unsafe_in_macros: 0,
+                    foreign_functions: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    foreign_statics: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    exported_functions: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    statics: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    static_mut_accesses: 0,
+                    send_sync_impls: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    marker_trait_impls: Count {
+                        safe: 0,
+                        unsafe_: 0
+                    },
+                    unsafe_blocks: 0,
+                    unsafe_operations: Some(UnsafeOperationKinds {
+                        unsafe_fn_calls: 1,
+                        ..Default::default()
+                    }),
+                    unsafe_scopes: Some(UnsafeScopeKinds {
+                        implicit_exprs: 1,
+                        explicit_exprs: 1,
+                        redundant_blocks: 0,
+                    }),
+                    dangerous_api_calls: BTreeMap::new(),
                 },
-                forbids_unsafe: false
+                inactive_counters: CounterBlock::default(),
+                forbids_unsafe: false,
+                lint_status: LintStatus::Allow,
+                allows_unsafe_locally: false,
+                unsafe_locations: file_content_unsafe_locations(IncludeTests::No),
+                functions: file_content_functions(IncludeTests::No),
+                safety_comments: SafetyCommentCoverage {
+                    documented: 0,
+                    total: 1,
+                },
+                approximate: false,
+                parse_error: None,
+                plugin_metrics: BTreeMap::new(),
             }
         )
     )]
@@ -221,12 +558,967 @@ mod tests {
         input_include_tests: IncludeTests,
         expected_rs_file_metrics: RsFileMetrics,
     ) {
-        let unsafe_in_string_result =
-            find_unsafe_in_string(FILE_CONTENT_STRING, input_include_tests);
+        let unsafe_in_string_result = find_unsafe_in_string(
+            FILE_CONTENT_STRING,
+            input_include_tests,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        );
 
         assert!(unsafe_in_string_result.is_ok());
         let unsafe_in_string = unsafe_in_string_result.unwrap();
 
         assert_eq!(unsafe_in_string, expected_rs_file_metrics);
     }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_macros() {
+        let src = "macro_rules! define {
+    ($name:ident) => {
+        pub unsafe extern \"C\" fn $name() {}
+        fn checked() -> u8 {
+            unsafe { *std::ptr::null() }
+        }
+    };
+}
+
+fn f(g: fn()) {
+    println!(\"{}\", unsafe { std::str::from_utf8_unchecked(b\"\") });
+    let _ = vec![g as unsafe fn()];
+}
+";
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert_eq!(rs_file_metrics.counters.unsafe_in_macros, 3);
+        assert_eq!(
+            rs_file_metrics.counters.functions,
+            Count {
+                safe: 1,
+                unsafe_: 0
+            }
+        );
+        assert_eq!(
+            rs_file_metrics
+                .unsafe_locations
+                .iter()
+                .map(|l| (l.kind, l.start.line))
+                .collect::<Vec<_>>(),
+            vec![
+                (UnsafeKind::Macro, 3),
+                (UnsafeKind::Macro, 5),
+                (UnsafeKind::Macro, 11)
+            ]
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_ffi() {
+        let src = "extern \"C\" {
+    fn abs(input: i32) -> i32;
+    static errno: i32;
+}
+
+#[no_mangle]
+pub extern \"C\" fn exported() {}
+
+extern fn callback() {}
+
+extern \"Rust\" fn rust_abi() {}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.foreign_functions,
+            Count {
+                safe: 0,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            counters.foreign_statics,
+            Count {
+                safe: 0,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            counters.exported_functions,
+            Count {
+                safe: 1,
+                unsafe_: 1
+            }
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_trait_methods() {
+        let src = "trait Buffer {
+    fn len(&self) -> usize;
+
+    unsafe fn get_unchecked(&self, index: usize) -> u8;
+
+    unsafe fn first_unchecked(&self) -> u8 {
+        self.get_unchecked(0)
+    }
+
+    fn first(&self) -> Option<u8> {
+        if self.len() > 0 {
+            Some(unsafe { self.first_unchecked() })
+        } else {
+            None
+        }
+    }
+}
+";
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rs_file_metrics.counters.methods,
+            Count {
+                safe: 2,
+                unsafe_: 2
+            }
+        );
+        assert_eq!(
+            rs_file_metrics.counters.exprs,
+            Count {
+                safe: 5,
+                unsafe_: 2
+            }
+        );
+        assert_eq!(
+            rs_file_metrics
+                .unsafe_locations
+                .iter()
+                .map(|location| (location.kind, location.start.line))
+                .collect::<Vec<_>>(),
+            vec![
+                (UnsafeKind::Method, 4),
+                (UnsafeKind::Method, 6),
+                (UnsafeKind::Block, 12)
+            ]
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_edition_2024_unsafe_extern() {
+        let src = "unsafe extern \"C\" {
+    pub safe fn abs(input: i32) -> i32;
+    pub unsafe fn strlen(p: *const u8) -> usize;
+    fn free(p: *mut u8);
+    #[link_name = \"errno_value\"]
+    safe static ERRNO: i32;
+    static ENVIRON: *const *const u8;
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.foreign_functions,
+            Count {
+                safe: 1,
+                unsafe_: 2
+            }
+        );
+        assert_eq!(
+            counters.foreign_statics,
+            Count {
+                safe: 1,
+                unsafe_: 1
+            }
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_edition_2024_unsafe_extern_cfg() {
+        let src = "unsafe extern \"C\" {
+    #[cfg(windows)]
+    pub safe fn abs(input: i32) -> i32;
+    #[cfg(unix)]
+    safe static ERRNO: i32;
+}
+";
+        let cfg_set =
+            CfgSet::from_target_cfgs(vec![(String::from("unix"), None)]);
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &cfg_set,
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rs_file_metrics.counters.foreign_functions,
+            Count::default()
+        );
+        assert_eq!(
+            rs_file_metrics.inactive_counters.foreign_functions,
+            Count {
+                safe: 1,
+                unsafe_: 0
+            }
+        );
+        assert_eq!(
+            rs_file_metrics.counters.foreign_statics,
+            Count {
+                safe: 1,
+                unsafe_: 0
+            }
+        );
+    }
+
+    #[rstest(
+        input_attribute,
+        expected_functions,
+        case("#[unsafe(no_mangle)]", Count { safe: 0, unsafe_: 1 }),
+        case(
+            "#[unsafe(export_name = \"exported_f\")]",
+            Count { safe: 0, unsafe_: 1 }
+        ),
+        case("#[inline]", Count { safe: 1, unsafe_: 0 })
+    )]
+    fn find_unsafe_in_string_test_unsafe_attributes(
+        input_attribute: &str,
+        expected_functions: Count,
+    ) {
+        let src = format!("{}\npub fn f() {{}}\n", input_attribute);
+        let counters = find_unsafe_in_string(
+            &src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(counters.functions, expected_functions);
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_bare_fn_trait_objects() {
+        let src = "fn call(f: &Fn(u8) -> u8) -> u8 {
+    unsafe { f(0) }
+}
+";
+        assert!(find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .is_err());
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_unsafe_operations() {
+        let src = "use std::arch::{asm, global_asm};
+use std::mem::transmute;
+
+global_asm!(\"nop\");
+
+static mut COUNTER: u32 = 0;
+
+union IntOrFloat {
+    i: u32,
+    f: f32,
+}
+
+impl IntOrFloat {
+    fn int(&self) -> u32 {
+        unsafe { self.i }
+    }
+}
+
+unsafe fn unchecked(p: *const u8) -> u8 {
+    *p
+}
+
+fn f(v: &[u8], u: IntOrFloat) -> u32 {
+    let p = v.as_ptr();
+    unsafe {
+        COUNTER += 1;
+        asm!(\"nop\");
+        let x: u32 = transmute(u.f);
+        let y = *p.add(1) + unchecked(p) + *(v.as_ptr() as *const u8);
+        let s = std::str::from_utf8_unchecked(v);
+        x + u32::from(y) + s.len() as u32
+    }
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.unsafe_operations,
+            Some(UnsafeOperationKinds {
+                raw_pointer_derefs: 3,
+                unsafe_fn_calls: 2,
+                union_field_accesses: 2,
+                static_mut_accesses: 1,
+                inline_asm: 2,
+                transmutes: 1,
+            })
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_no_unsafe_operations() {
+        let src = "fn f(v: &[u8]) -> u8 {
+    let p = v.as_ptr();
+    let first = &v[0];
+    *first + p as usize as u8
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(counters.unsafe_operations, None);
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_integer_arithmetic_methods() {
+        let src = "fn f(a: Meters, b: Meters, p: *const u32) -> u32 {
+    let total = a.add(b);
+    let next = p.add(1);
+    unsafe { *total + *next + *p.cast::<u32>() }
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.unsafe_operations,
+            Some(UnsafeOperationKinds {
+                raw_pointer_derefs: 2,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_unsafe_fn_paths() {
+        let src = "unsafe fn foo() {}
+
+struct Wrapper;
+
+impl Wrapper {
+    unsafe fn get(&self) {}
+
+    unsafe fn f(&self) {
+        foo();
+        self::foo();
+        other::foo();
+        Self::get(self);
+        Wrapper::get(self);
+        Other::get(self);
+        get();
+    }
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.unsafe_operations,
+            Some(UnsafeOperationKinds {
+                unsafe_fn_calls: 4,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_unsafe_scopes() {
+        let src = "unsafe fn f(p: *const u8) -> u8 {
+    fn safe(x: u8) -> u8 {
+        x + 1
+    }
+    let x = safe(*p);
+    unsafe {
+        unsafe { x + *p }
+    }
+}
+
+fn g(p: *const u8) -> u8 {
+    unsafe {
+        fn nested(p: *const u8) -> u8 {
+            unsafe { *p }
+        }
+        nested(p)
+    }
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.unsafe_scopes,
+            Some(UnsafeScopeKinds {
+                implicit_exprs: 2,
+                explicit_exprs: 4,
+                redundant_blocks: 1,
+            })
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_dangerous_api_calls() {
+        let src = "use std::mem::{self, MaybeUninit};
+
+fn f(v: &mut Vec<u8>) -> u32 {
+    unsafe {
+        v.set_len(0);
+        let x = MaybeUninit::<u32>::uninit().assume_init();
+        mem::transmute::<f32, u32>(1.0) + x + *v.get_unchecked(0) as u32
+    }
+}
+
+fn h(file: &mut File, lock: &RwLock<u8>, buf: &mut [u8]) {
+    file.read(buf).unwrap();
+    *lock.write().unwrap() = 0;
+}
+
+#[cfg(any())]
+fn g(b: [u8; 4]) -> u32 {
+    unsafe { std::mem::transmute(b) }
+}
+";
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::builtin(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rs_file_metrics.counters.dangerous_api_calls,
+            vec![
+                (String::from("MaybeUninit.assume_init"), 1),
+                (String::from("Vec.set_len"), 1),
+                (String::from("mem::transmute"), 1),
+                (String::from("slice.get_unchecked"), 1),
+            ]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>()
+        );
+        assert_eq!(
+            rs_file_metrics.inactive_counters.dangerous_api_calls,
+            vec![(String::from("mem::transmute"), 1)]
+                .into_iter()
+                .collect::<BTreeMap<_, _>>()
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_safety_comments() {
+        let src = "struct A(*const u8);
+
+// SAFETY: the pointer is never dereferenced.
+unsafe impl Send for A {}
+
+unsafe impl Sync for A {}
+
+fn f(p: *const u8) -> u8 {
+    // SAFETY: the caller guarantees that `p` is valid.
+    let x = u8::from(
+        unsafe { *p },
+    );
+    let y = unsafe { *p };
+    x + /* SAFETY: see above. */ unsafe { *p } + y
+}
+
+#[cfg(any())]
+fn g(p: *const u8) -> u8 {
+    unsafe { *p }
+}
+";
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rs_file_metrics.safety_comments,
+            SafetyCommentCoverage {
+                documented: 3,
+                total: 5
+            }
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_functions() {
+        let src = "mod inner {
+    struct A(*const u8);
+
+    impl<'a> From<&'a A> for u8 {
+        fn from(a: &'a A) -> u8 {
+            let read = || unsafe { a.0.read() };
+            read() + unsafe { *a.0 }
+        }
+    }
+
+    impl A {
+        unsafe fn get(&self) -> u8 {
+            fn helper(p: *const u8) -> u8 {
+                unsafe { *p }
+            }
+            helper(self.0)
+        }
+    }
+}
+
+#[cfg(any())]
+unsafe fn f() {}
+";
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        let functions = rs_file_metrics
+            .functions
+            .iter()
+            .map(|function| {
+                (
+                    function.path.as_str(),
+                    function.start.line,
+                    function.is_unsafe,
+                    function.unsafe_exprs,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            functions,
+            vec![
+                ("inner::u8::from", 5, false, 4),
+                ("inner::A::get", 12, true, 2),
+                ("inner::A::get::helper", 13, false, 1),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_static_mut() {
+        let src = "static GREETING: &str = \"hello\";
+static mut COUNTER: u32 = 0;
+
+mod inner {
+    pub static mut FLAG: bool = false;
+
+    fn set() {
+        unsafe {
+            self::FLAG = true;
+            super::COUNTER = 0;
+        }
+    }
+}
+
+fn increment() -> u32 {
+    unsafe {
+        COUNTER += 1;
+        inner::FLAG = true;
+        self::inner::FLAG = false;
+        other::FLAG = true;
+        crate::COUNTER
+    }
+}
+
+fn shadowed(COUNTER: u32) -> u32 {
+    COUNTER
+}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.statics,
+            Count {
+                safe: 1,
+                unsafe_: 2
+            }
+        );
+        assert_eq!(counters.static_mut_accesses, 6);
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_cfg() {
+        let src = "#[cfg(windows)]
+unsafe fn windows_only() {}
+
+#[cfg(all(unix, not(target_os = \"macos\")))]
+fn unix_only() {
+    #[cfg(target_pointer_width = \"32\")]
+    let x = unsafe { windows_only() };
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    unsafe fn windows_test_only() {}
+}
+
+#[cfg(test)]
+mod unix_tests {
+    unsafe fn unix_test_only() {}
+}
+";
+        let cfg_set = CfgSet::from_target_cfgs(vec![
+            (String::from("unix"), None),
+            (String::from("target_os"), Some(String::from("linux"))),
+            (
+                String::from("target_pointer_width"),
+                Some(String::from("64")),
+            ),
+        ]);
+        let rs_file_metrics = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &cfg_set,
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rs_file_metrics.counters.functions,
+            Count {
+                safe: 1,
+                unsafe_: 0
+            }
+        );
+        assert_eq!(
+            rs_file_metrics.inactive_counters.functions,
+            Count {
+                safe: 0,
+                unsafe_: 2
+            }
+        );
+        assert_eq!(
+            rs_file_metrics.inactive_counters.exprs,
+            Count {
+                safe: 0,
+                unsafe_: 1
+            }
+        );
+        assert!(rs_file_metrics.unsafe_locations.is_empty());
+    }
+
+    #[rstest]
+    fn find_unsafe_in_string_test_marker_traits() {
+        let src = "struct Handle(*mut u8);
+
+unsafe impl Send for Handle {}
+unsafe impl std::marker::Sync for Handle {}
+impl !Send for Guard {}
+
+unsafe impl GlobalAlloc for Handle {}
+unsafe impl bytemuck::Pod for Handle {}
+unsafe impl Custom for Handle {}
+impl Clone for Handle {}
+";
+        let counters = find_unsafe_in_string(
+            src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap()
+        .counters;
+
+        assert_eq!(
+            counters.item_impls,
+            Count {
+                safe: 2,
+                unsafe_: 5
+            }
+        );
+        assert_eq!(
+            counters.send_sync_impls,
+            Count {
+                safe: 1,
+                unsafe_: 2
+            }
+        );
+        assert_eq!(
+            counters.marker_trait_impls,
+            Count {
+                safe: 0,
+                unsafe_: 2
+            }
+        );
+    }
+
+    #[rstest(
+        input_src,
+        expected_lint_status,
+        expected_allows_unsafe_locally,
+        case("#![forbid(unsafe_code)]", LintStatus::Forbid, false),
+        case(
+            "#![forbid(missing_docs, unsafe_code)]",
+            LintStatus::Forbid,
+            false
+        ),
+        case(
+            "#![cfg_attr(not(test), forbid(unsafe_code))]",
+            LintStatus::ConditionalForbid,
+            false
+        ),
+        case(
+            "#![cfg_attr(not(test), deny(unsafe_code))]",
+            LintStatus::ConditionalDeny,
+            false
+        ),
+        case(
+            "#![deny(unsafe_code)]\n#[allow(unsafe_code)]\nfn f() {}",
+            LintStatus::Deny,
+            true
+        ),
+        case(
+            "#![warn(unsafe_code)]\nmod m {\n    #![allow(unsafe_code)]\n}",
+            LintStatus::Allow,
+            true
+        ),
+        case("#![forbid(missing_docs)]", LintStatus::Allow, false)
+    )]
+    fn find_unsafe_in_string_test_lint_status(
+        input_src: &str,
+        expected_lint_status: LintStatus,
+        expected_allows_unsafe_locally: bool,
+    ) {
+        let rs_file_metrics = find_unsafe_in_string(
+            input_src,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+
+        assert_eq!(rs_file_metrics.lint_status, expected_lint_status);
+        assert_eq!(
+            rs_file_metrics.forbids_unsafe,
+            expected_lint_status == LintStatus::Forbid
+        );
+        assert_eq!(
+            rs_file_metrics.allows_unsafe_locally,
+            expected_allows_unsafe_locally
+        );
+    }
+
+    #[rstest(
+        input_include_tests,
+        case(IncludeTests::Yes),
+        case(IncludeTests::No)
+    )]
+    fn find_unsafe_in_string_test_counters_do_not_depend_on_item_order(
+        input_include_tests: IncludeTests,
+    ) {
+        let counters = |items: &[&str]| {
+            find_unsafe_in_string(
+                &items.join("\n\n"),
+                input_include_tests,
+                &CfgSet::new(),
+                &DangerousApiRules::new(),
+            )
+            .unwrap()
+            .counters
+        };
+        let expected_counters = counters(&REORDERED_ITEMS);
+
+        for items in permutations(&REORDERED_ITEMS) {
+            assert_eq!(counters(&items), expected_counters, "{:#?}", items);
+        }
+    }
+
+    #[rstest(
+        input_include_tests,
+        expected_unsafe_exprs,
+        case(IncludeTests::Yes, 7),
+        case(IncludeTests::No, 6)
+    )]
+    fn find_unsafe_in_string_test_counters_are_the_sum_of_item_counters(
+        input_include_tests: IncludeTests,
+        expected_unsafe_exprs: u64,
+    ) {
+        let counters = |src: &str| {
+            find_unsafe_in_string(
+                src,
+                input_include_tests,
+                &CfgSet::new(),
+                &DangerousApiRules::new(),
+            )
+            .unwrap()
+            .counters
+        };
+
+        let item_counters = REORDERED_ITEMS
+            .iter()
+            .map(|item| counters(item))
+            .fold(CounterBlock::default(), |sum, counters| sum + counters);
+
+        assert_eq!(counters(&REORDERED_ITEMS.join("\n\n")), item_counters);
+        assert_eq!(item_counters.exprs.unsafe_, expected_unsafe_exprs);
+    }
+
+    /// Items whose counters do not depend on the other items of the file,
+    /// scanned in every order.
+    const REORDERED_ITEMS: [&str; 6] = [
+        "#[no_mangle]\npub extern \"C\" fn exported(x: u8) -> u8 {\n    x + 1\n}",
+        "#[export_name = \"renamed\"]\npub fn renamed(x: u8) -> u8 {\n    x * 2\n}",
+        "pub unsafe fn unchecked(p: *const u8) -> u8 {\n    *p + 1\n}",
+        "pub fn checked(p: &u8) -> u8 {\n    let q = p as *const u8;\n    unsafe { unsafe { *q } }\n}",
+        "trait T {\n    unsafe fn t(&self) -> u8 {\n        compute(0)\n    }\n}\n\nimpl T for u8 {\n    unsafe fn t(&self) -> u8 {\n        compute(1) + 1\n    }\n}",
+        "#[cfg(test)]\nmod tests {\n    #[test]\n    fn test_1() {\n        unsafe { compute(3) };\n    }\n}",
+    ];
+
+    fn permutations<'a>(items: &[&'a str]) -> Vec<Vec<&'a str>> {
+        if items.is_empty() {
+            return vec![Vec::new()];
+        }
+        (0..items.len())
+            .flat_map(|index| {
+                let mut rest = items.to_vec();
+                let item = rest.remove(index);
+                permutations(&rest).into_iter().map(move |mut permutation| {
+                    permutation.insert(0, item);
+                    permutation
+                })
+            })
+            .collect()
+    }
+
+    fn file_content_unsafe_locations(
+        include_tests: IncludeTests,
+    ) -> Vec<UnsafeLocation> {
+        let location =
+            |kind, start: (usize, usize), end: (usize, usize)| UnsafeLocation {
+                file: Default::default(),
+                start: LineColumn {
+                    line: start.0,
+                    column: start.1,
+                },
+                end: LineColumn {
+                    line: end.0,
+                    column: end.1,
+                },
+                kind,
+            };
+        let mut locations = vec![
+            location(UnsafeKind::Function, (3, 0), (5, 1)),
+            location(UnsafeKind::Block, (8, 32), (10, 5)),
+            location(UnsafeKind::Function, (13, 0), (16, 1)),
+            location(UnsafeKind::Function, (18, 0), (21, 1)),
+        ];
+        if include_tests == IncludeTests::Yes {
+            locations.push(location(UnsafeKind::Block, (29, 8), (31, 9)));
+        }
+        locations
+    }
+
+    fn file_content_functions(
+        include_tests: IncludeTests,
+    ) -> Vec<FunctionMetrics> {
+        let function = |path: &str,
+                        start: (usize, usize),
+                        end: (usize, usize),
+                        is_unsafe,
+                        unsafe_exprs| FunctionMetrics {
+            path: path.to_string(),
+            file: Default::default(),
+            start: LineColumn {
+                line: start.0,
+                column: start.1,
+            },
+            end: LineColumn {
+                line: end.0,
+                column: end.1,
+            },
+            is_unsafe,
+            unsafe_exprs,
+        };
+        let mut functions = vec![
+            function("f", (3, 0), (5, 1), true, 1),
+            function("g", (7, 0), (11, 1), false, 1),
+            function("h", (13, 0), (16, 1), true, 0),
+            function("g", (18, 0), (21, 1), true, 0),
+        ];
+        if include_tests == IncludeTests::Yes {
+            functions.push(function(
+                "tests::test_1",
+                (27, 4),
+                (32, 5),
+                false,
+                0,
+            ));
+        }
+        functions
+    }
 }
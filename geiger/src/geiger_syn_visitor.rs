@@ -1,14 +1,76 @@
+use std::collections::HashSet;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
 use super::{
-    file_forbids_unsafe, has_unsafe_attributes, is_test_fn, is_test_mod,
-    IncludeTests, RsFileMetrics,
+    file_allows_unsafe_locally, file_lint_status, file_static_mut_paths,
+    has_unsafe_attributes, is_test_fn, path_segments,
+    resolves_to_declared_path, type_name, CfgSet, IncludeTests, RsFileMetrics,
 };
 
-use syn::{visit, Expr, ImplItemMethod, ItemFn, ItemImpl, ItemMod, ItemTrait};
+use crate::dangerous_api::DangerousApiRules;
+use crate::safety_comments::SafetyComments;
+use crate::unsafe_operations::{
+    is_asm_macro_path, is_transmute_path, UnsafeOperation, UnsafeOperationNames,
+};
+
+use cargo_geiger_serde::{
+    FunctionMetrics, LineColumn, LintStatus, UnsafeKind, UnsafeLocation,
+    UnsafeScopeKinds,
+};
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree};
+use syn::ext::IdentExt;
+use syn::parse::{ParseStream, Parser};
+use syn::spanned::Spanned;
+use syn::{
+    visit, Abi, Arm, Attribute, Expr, ExprCall, ExprMethodCall, ForeignItem,
+    ForeignItemFn, ForeignItemStatic, ImplItem, ImplItemFn, Item, ItemFn,
+    ItemImpl, ItemMod, ItemStatic, ItemTrait, Local, Macro, Path,
+    StaticMutability, Stmt, StmtMacro, TraitItem, TraitItemFn, UnOp,
+};
+
+/// Well-known unsafe marker traits, other than `Send` and `Sync`, whose
+/// implementations assert invariants the compiler cannot check. Matched on
+/// the last segment of the trait path.
+const MARKER_TRAITS: [&str; 11] = [
+    "GlobalAlloc",
+    "Allocator",
+    "TrustedLen",
+    "Pod",
+    "Zeroable",
+    "AnyBitPattern",
+    "NoUninit",
+    "TransparentWrapper",
+    "FromBytes",
+    "FromZeroes",
+    "AsBytes",
+];
+
+/// How the `#[cfg(..)]` attributes of a node affect the scan.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CfgActivity {
+    /// The node is compiled for the target.
+    Active,
+    /// The node is not compiled for the target, it is counted separately.
+    Inactive,
+    /// The node is only compiled for tests, which are not scanned.
+    Skipped,
+}
 
 pub struct GeigerSynVisitor {
     /// Count unsafe usage inside tests
     include_tests: IncludeTests,
 
+    /// The target cfg set with the `test` option enabled.
+    cfg_set_with_tests: CfgSet,
+
+    /// The target cfg set with the `test` option disabled.
+    cfg_set_without_tests: CfgSet,
+
+    /// Whether the visitor is inside code that is not compiled for the
+    /// target, in which case `metrics.counters` holds the inactive counters.
+    inactive: bool,
+
     /// The resulting data from a single file scan.
     pub metrics: RsFileMetrics,
 
@@ -20,60 +82,324 @@ pub struct GeigerSynVisitor {
     /// This is needed since unsafe scopes can be nested and we need to know
     /// when we leave the outmost unsafe scope and get back into a safe scope.
     unsafe_scopes: u32,
+
+    /// The number of nested explicit `unsafe` blocks that the visitor is
+    /// currently in, within the innermost function. Expressions in unsafe
+    /// scopes outside of these blocks are in the body of an unsafe function.
+    unsafe_blocks: u32,
+
+    /// Paths of the `static mut` items declared in the file, relative to the
+    /// file, used to find accesses to them.
+    static_mut_paths: HashSet<Vec<String>>,
+
+    /// Names of the inline modules enclosing the node being visited, used to
+    /// resolve relative paths.
+    module_path: Vec<String>,
+
+    /// Names declared in the file, used to classify the operations requiring
+    /// `unsafe`.
+    unsafe_operation_names: UnsafeOperationNames,
+
+    /// Whether the visitor is inside an `impl` block of a union, where field
+    /// accesses through `self` are union field accesses.
+    in_union_impl: bool,
+
+    /// The name of the type or trait of the innermost `impl` or `trait` block
+    /// being visited, which `Self` refers to.
+    self_type: Option<String>,
+
+    /// Known-dangerous APIs whose calls are counted.
+    dangerous_api_rules: DangerousApiRules,
+
+    /// The source of the file, searched for `// SAFETY:` comments.
+    safety_comments: SafetyComments,
+
+    /// The span of the innermost statement being visited. A `// SAFETY:`
+    /// comment before a statement such as `let x = f(unsafe { .. });`
+    /// documents the `unsafe` blocks in it.
+    statement_span: Option<Span>,
+
+    /// Names of the inline modules, impl types and functions enclosing the
+    /// node being visited, joined into the paths of `metrics.functions`.
+    item_path: Vec<String>,
+
+    /// Index in `metrics.functions` of the innermost function being
+    /// visited, if it is recorded.
+    function_index: Option<usize>,
+}
+
+/// An unsafe scope entered by the visitor, exited when the guard is dropped
+/// so that entering and exiting scopes always match, whatever the path out
+/// of the visited node.
+struct UnsafeScopeGuard<'v> {
+    visitor: &'v mut GeigerSynVisitor,
+    /// `unsafe_scopes` and `unsafe_blocks` outside of the scope
+    outer_unsafe_scopes: u32,
+    outer_unsafe_blocks: u32,
+}
+
+impl Deref for UnsafeScopeGuard<'_> {
+    type Target = GeigerSynVisitor;
+
+    fn deref(&self) -> &Self::Target {
+        self.visitor
+    }
+}
+
+impl DerefMut for UnsafeScopeGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.visitor
+    }
+}
+
+impl Drop for UnsafeScopeGuard<'_> {
+    fn drop(&mut self) {
+        self.visitor.unsafe_scopes = self.outer_unsafe_scopes;
+        self.visitor.unsafe_blocks = self.outer_unsafe_blocks;
+    }
 }
 
 impl GeigerSynVisitor {
-    pub fn new(include_tests: IncludeTests) -> Self {
+    pub fn new(
+        src: &str,
+        include_tests: IncludeTests,
+        cfg_set: &CfgSet,
+        dangerous_api_rules: &DangerousApiRules,
+    ) -> Self {
+        let mut cfg_set_with_tests = cfg_set.clone();
+        cfg_set_with_tests.insert(String::from("test"), None);
+        let mut cfg_set_without_tests = cfg_set.clone();
+        cfg_set_without_tests.insert_known_name("test");
         GeigerSynVisitor {
             include_tests,
+            cfg_set_with_tests,
+            cfg_set_without_tests,
+            inactive: false,
             metrics: Default::default(),
             unsafe_scopes: 0,
+            unsafe_blocks: 0,
+            static_mut_paths: HashSet::new(),
+            module_path: Vec::new(),
+            unsafe_operation_names: Default::default(),
+            in_union_impl: false,
+            self_type: None,
+            dangerous_api_rules: dangerous_api_rules.clone(),
+            safety_comments: SafetyComments::from_source(src),
+            statement_span: None,
+            item_path: Vec::new(),
+            function_index: None,
+        }
+    }
+
+    /// Enter the body of a function, which is an unsafe scope if
+    /// `unsafe_body` is true. The body of a nested function is not part of
+    /// the enclosing unsafe function or block. The scope is exited when the
+    /// returned guard is dropped, the nodes in it are visited through the
+    /// guard.
+    fn function_body(&mut self, unsafe_body: bool) -> UnsafeScopeGuard<'_> {
+        let outer_unsafe_scopes =
+            mem::replace(&mut self.unsafe_scopes, u32::from(unsafe_body));
+        let outer_unsafe_blocks = mem::replace(&mut self.unsafe_blocks, 0);
+        UnsafeScopeGuard {
+            visitor: self,
+            outer_unsafe_scopes,
+            outer_unsafe_blocks,
         }
     }
 
-    pub fn enter_unsafe_scope(&mut self) {
+    /// Enter the unsafe scope of an explicit `unsafe` block.
+    fn unsafe_block(&mut self) -> UnsafeScopeGuard<'_> {
+        let (outer_unsafe_scopes, outer_unsafe_blocks) =
+            (self.unsafe_scopes, self.unsafe_blocks);
         self.unsafe_scopes += 1;
+        self.unsafe_blocks += 1;
+        UnsafeScopeGuard {
+            visitor: self,
+            outer_unsafe_scopes,
+            outer_unsafe_blocks,
+        }
     }
 
-    pub fn exit_unsafe_scope(&mut self) {
-        self.unsafe_scopes -= 1;
+    /// Whether a node with these attributes is compiled for the target. Code
+    /// that is only compiled for tests is skipped unless tests are included.
+    fn cfg_activity(&self, attrs: &[Attribute]) -> CfgActivity {
+        let with_tests = self.cfg_set_with_tests.evaluate_attributes(attrs);
+        let without_tests =
+            self.cfg_set_without_tests.evaluate_attributes(attrs);
+        match (with_tests, without_tests) {
+            (Some(false), Some(false)) => CfgActivity::Inactive,
+            (_, Some(false)) if IncludeTests::No == self.include_tests => {
+                CfgActivity::Skipped
+            }
+            _ => CfgActivity::Active,
+        }
     }
-}
 
-impl<'ast> visit::Visit<'ast> for GeigerSynVisitor {
-    fn visit_file(&mut self, i: &'ast syn::File) {
-        self.metrics.forbids_unsafe = file_forbids_unsafe(i);
-        syn::visit::visit_file(self, i);
+    /// Visit a node, counting it in the inactive counters if its `#[cfg(..)]`
+    /// attributes exclude it from the target.
+    fn visit_with_cfg<F>(&mut self, attrs: &[Attribute], visit: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        match self.cfg_activity(attrs) {
+            CfgActivity::Skipped => {}
+            CfgActivity::Inactive if !self.inactive => {
+                self.swap_inactive_counters();
+                visit(self);
+                self.swap_inactive_counters();
+            }
+            _ => visit(self),
+        }
     }
 
-    /// Free-standing functions
-    fn visit_item_fn(&mut self, item_fn: &ItemFn) {
-        if IncludeTests::No == self.include_tests && is_test_fn(item_fn) {
+    fn swap_inactive_counters(&mut self) {
+        self.inactive = !self.inactive;
+        mem::swap(
+            &mut self.metrics.counters,
+            &mut self.metrics.inactive_counters,
+        );
+    }
+
+    /// Record where an unsafe item or block was found. The file path is left
+    /// empty, it is filled in by the caller when known. Nothing is recorded
+    /// for code that is not compiled for the target.
+    fn record_unsafe_location(&mut self, kind: UnsafeKind, span: Span) {
+        if self.inactive {
             return;
         }
-        let unsafe_fn =
-            item_fn.sig.unsafety.is_some() || has_unsafe_attributes(item_fn);
-        if unsafe_fn {
-            self.enter_unsafe_scope()
+        let (start, end) = (span.start(), span.end());
+        self.metrics.unsafe_locations.push(UnsafeLocation {
+            file: Default::default(),
+            start: LineColumn {
+                line: start.line,
+                column: start.column,
+            },
+            end: LineColumn {
+                line: end.line,
+                column: end.column,
+            },
+            kind,
+        });
+    }
+
+    /// Count an `unsafe` block or `unsafe impl`, and whether it is documented
+    /// by a `// SAFETY:` comment before it or before the statement it is
+    /// part of. Nothing is counted for code that is not compiled for the
+    /// target.
+    fn count_safety_comment(&mut self, span: Span) {
+        if self.inactive {
+            return;
         }
-        self.metrics.counters.functions.count(unsafe_fn);
-        visit::visit_item_fn(self, item_fn);
-        if item_fn.sig.unsafety.is_some() {
-            self.exit_unsafe_scope()
+        let documented = self.safety_comments.documents(span.start())
+            || self.statement_span.is_some_and(|statement_span| {
+                self.safety_comments.documents(statement_span.start())
+            });
+        let coverage = &mut self.metrics.safety_comments;
+        coverage.total += 1;
+        coverage.documented += documented as u64;
+    }
+
+    /// Record a function or method, and visit its body with the unsafe
+    /// expressions counted in its record. Nothing is recorded for code that
+    /// is not compiled for the target.
+    fn visit_function<F>(
+        &mut self,
+        name: &Ident,
+        is_unsafe: bool,
+        span: Span,
+        visit: F,
+    ) where
+        F: FnOnce(&mut Self),
+    {
+        self.item_path.push(name.unraw().to_string());
+        let function_index = if self.inactive {
+            None
+        } else {
+            let (start, end) = (span.start(), span.end());
+            self.metrics.functions.push(FunctionMetrics {
+                path: self.item_path.join("::"),
+                file: Default::default(),
+                start: LineColumn {
+                    line: start.line,
+                    column: start.column,
+                },
+                end: LineColumn {
+                    line: end.line,
+                    column: end.column,
+                },
+                is_unsafe,
+                unsafe_exprs: 0,
+            });
+            Some(self.metrics.functions.len() - 1)
+        };
+        let outer_function_index =
+            mem::replace(&mut self.function_index, function_index);
+        visit(self);
+        self.function_index = outer_function_index;
+        self.item_path.pop();
+    }
+
+    /// Count an expression as safe or unsafe, depending on the scope it is
+    /// in. Unsafe expressions are counted by kind of scope, and in the
+    /// innermost function as well.
+    fn count_expression(&mut self) {
+        let unsafe_scope = self.unsafe_scopes > 0;
+        self.metrics.counters.exprs.count(unsafe_scope);
+        if unsafe_scope {
+            let explicit = self.unsafe_blocks > 0;
+            let unsafe_scopes = self.unsafe_scope_kinds();
+            if explicit {
+                unsafe_scopes.explicit_exprs += 1;
+            } else {
+                unsafe_scopes.implicit_exprs += 1;
+            }
+        }
+        if let (true, false, Some(index)) =
+            (unsafe_scope, self.inactive, self.function_index)
+        {
+            self.metrics.functions[index].unsafe_exprs += 1;
         }
     }
 
-    fn visit_expr(&mut self, i: &Expr) {
+    /// Whether the path refers to one of the `static mut` items of the file.
+    /// Local bindings shadowing the item and `use` declarations are not taken
+    /// into account.
+    fn is_static_mut_path(&self, path: &Path) -> bool {
+        resolves_to_declared_path(
+            &self.static_mut_paths,
+            &self.module_path,
+            &path_segments(path),
+        )
+    }
+
+    /// Count an expression, which is compiled for the target or counted as
+    /// inactive, and visit its subexpressions.
+    fn count_expr(&mut self, i: &Expr) {
         // Total number of expressions of any type
         match i {
             Expr::Unsafe(i) => {
-                self.enter_unsafe_scope();
-                visit::visit_expr_unsafe(self, i);
-                self.exit_unsafe_scope();
+                if self.unsafe_blocks > 0 {
+                    self.unsafe_scope_kinds().redundant_blocks += 1;
+                }
+                self.record_unsafe_location(UnsafeKind::Block, i.span());
+                self.count_safety_comment(i.span());
+                let mut visitor = self.unsafe_block();
+                visit::visit_expr_unsafe(&mut *visitor, i);
             }
-            Expr::Path(_) | Expr::Lit(_) => {
+            Expr::Path(expr_path) => {
                 // Do not count. The expression `f(x)` should count as one
                 // expression, not three.
+                if self.unsafe_scopes > 0
+                    && self.is_static_mut_path(&expr_path.path)
+                {
+                    self.metrics.counters.static_mut_accesses += 1;
+                    self.count_unsafe_operation(
+                        UnsafeOperation::StaticMutAccess,
+                    );
+                }
+            }
+            Expr::Lit(_) => {
+                // Do not count, see above.
             }
             other => {
                 // TODO: Print something pretty here or gather the data for later
@@ -81,50 +407,578 @@ impl<'ast> visit::Visit<'ast> for GeigerSynVisitor {
                 // if self.verbosity == Verbosity::Verbose && self.unsafe_scopes > 0 {
                 //     println!("{:#?}", other);
                 // }
-                self.metrics.counters.exprs.count(self.unsafe_scopes > 0);
+                self.count_expression();
+                if self.unsafe_scopes > 0 {
+                    if let Some(operation) = self.unsafe_operation(other) {
+                        self.count_unsafe_operation(operation);
+                    }
+                }
                 visit::visit_expr(self, other);
             }
         }
     }
 
-    fn visit_item_mod(&mut self, i: &ItemMod) {
-        if IncludeTests::No == self.include_tests && is_test_mod(i) {
+    /// Classify an expression inside an unsafe scope as one of the operations
+    /// requiring `unsafe`, if it is recognized as one.
+    fn unsafe_operation(&self, i: &Expr) -> Option<UnsafeOperation> {
+        let names = &self.unsafe_operation_names;
+        match i {
+            Expr::Unary(expr_unary)
+                if matches!(expr_unary.op, UnOp::Deref(_))
+                    && names.is_raw_pointer(&expr_unary.expr) =>
+            {
+                Some(UnsafeOperation::RawPointerDeref)
+            }
+            Expr::Call(expr_call) => match &*expr_call.func {
+                Expr::Path(expr_path) if is_transmute_path(&expr_path.path) => {
+                    Some(UnsafeOperation::Transmute)
+                }
+                Expr::Path(expr_path)
+                    if names.is_unsafe_fn_path(
+                        &expr_path.path,
+                        &self.module_path,
+                        self.self_type.as_deref(),
+                    ) =>
+                {
+                    Some(UnsafeOperation::UnsafeFnCall)
+                }
+                _ => None,
+            },
+            Expr::Field(expr_field)
+                if names.is_union_value(&expr_field.base)
+                    || self.in_union_impl
+                        && matches!(
+                            &*expr_field.base,
+                            Expr::Path(expr_path)
+                                if expr_path.path.is_ident("self")
+                        ) =>
+            {
+                Some(UnsafeOperation::UnionFieldAccess)
+            }
+            _ => None,
+        }
+    }
+
+    fn count_dangerous_api_call(&mut self, rule: Option<String>) {
+        if let Some(rule) = rule {
+            *self
+                .metrics
+                .counters
+                .dangerous_api_calls
+                .entry(rule)
+                .or_insert(0) += 1;
+        }
+    }
+
+    fn unsafe_scope_kinds(&mut self) -> &mut UnsafeScopeKinds {
+        self.metrics
+            .counters
+            .unsafe_scopes
+            .get_or_insert_with(Default::default)
+    }
+
+    fn count_unsafe_operation(&mut self, operation: UnsafeOperation) {
+        operation.count(
+            self.metrics
+                .counters
+                .unsafe_operations
+                .get_or_insert_with(Default::default),
+        );
+    }
+
+    /// Best effort search for `unsafe` blocks and functions in the tokens of
+    /// a macro definition or invocation, since these are never parsed into a
+    /// syntax tree.
+    fn count_unsafe_in_tokens(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<TokenTree>>();
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(group) => {
+                    self.count_unsafe_in_tokens(group.stream())
+                }
+                TokenTree::Ident(ident) if ident == "unsafe" => {
+                    if let Some(TokenTree::Group(group)) = tokens.get(index + 1)
+                    {
+                        if group.delimiter() == Delimiter::Brace {
+                            self.metrics.counters.unsafe_in_macros += 1;
+                            self.record_unsafe_location(
+                                UnsafeKind::Macro,
+                                join_spans(ident.span(), group.span()),
+                            );
+                        }
+                    }
+                }
+                TokenTree::Ident(ident)
+                    if ident == "fn" && is_fn_name(&tokens[index + 1..]) =>
+                {
+                    if let Some(unsafe_span) =
+                        find_unsafe_fn_qualifier(&tokens[..index])
+                    {
+                        self.metrics.counters.unsafe_in_macros += 1;
+                        let end_span = find_fn_body_span(&tokens[index..]);
+                        self.record_unsafe_location(
+                            UnsafeKind::Macro,
+                            join_spans(unsafe_span, end_span),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Function definitions are followed by a name, which distinguishes them from
+/// function pointer types such as `fn(u8) -> u8`. Names starting with `$` are
+/// accepted as well since they are common in `macro_rules!` bodies.
+pub fn is_fn_name(tokens_after_fn: &[TokenTree]) -> bool {
+    match tokens_after_fn {
+        [TokenTree::Ident(_), ..] => true,
+        [TokenTree::Punct(punct), TokenTree::Ident(_), ..] => {
+            punct.as_char() == '$'
+        }
+        _ => false,
+    }
+}
+
+/// Returns the span of the `unsafe` keyword if the tokens right before `fn`
+/// are `unsafe`, `unsafe extern` or `unsafe extern "abi"`.
+pub fn find_unsafe_fn_qualifier(
+    tokens_before_fn: &[TokenTree],
+) -> Option<Span> {
+    let qualifiers = match tokens_before_fn {
+        [.., TokenTree::Ident(e), TokenTree::Literal(_)] if e == "extern" => {
+            &tokens_before_fn[..tokens_before_fn.len() - 2]
+        }
+        [.., TokenTree::Ident(e)] if e == "extern" => {
+            &tokens_before_fn[..tokens_before_fn.len() - 1]
+        }
+        _ => tokens_before_fn,
+    };
+    match qualifiers.last() {
+        Some(TokenTree::Ident(ident)) if ident == "unsafe" => {
+            Some(ident.span())
+        }
+        _ => None,
+    }
+}
+
+/// The span of the body of a function, or of the `fn` keyword if the
+/// function has no body.
+pub fn find_fn_body_span(tokens_from_fn: &[TokenTree]) -> Span {
+    tokens_from_fn
+        .iter()
+        .take_while(|token| {
+            !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ';')
+        })
+        .find_map(|token| match token {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                Some(group.span())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| tokens_from_fn[0].span())
+}
+
+/// `extern fn` without an explicit ABI string defaults to the "C" ABI.
+fn is_non_rust_abi(abi: &Option<Abi>) -> bool {
+    match abi {
+        Some(Abi {
+            name: Some(name), ..
+        }) => name.value() != "Rust",
+        Some(Abi { name: None, .. }) => true,
+        None => false,
+    }
+}
+
+/// Returns the keyword following `safe` in a foreign item such as
+/// `#[link_name = "f"] pub safe fn f();`, skipping the outer attributes and
+/// the visibility before it.
+fn safe_foreign_item_keyword(tokens: &TokenStream) -> Option<String> {
+    let tokens = tokens.clone().into_iter().collect::<Vec<TokenTree>>();
+    let mut index = 0;
+    loop {
+        match tokens.get(index)? {
+            TokenTree::Punct(punct) if punct.as_char() == '#' => index += 2,
+            TokenTree::Ident(ident) if ident == "pub" => {
+                index += 1;
+                if let Some(TokenTree::Group(group)) = tokens.get(index) {
+                    if group.delimiter() == Delimiter::Parenthesis {
+                        index += 1;
+                    }
+                }
+            }
+            TokenTree::Ident(ident) if ident == "safe" => break,
+            _ => return None,
+        }
+    }
+    match tokens.get(index + 1)? {
+        TokenTree::Ident(keyword) => Some(keyword.to_string()),
+        _ => None,
+    }
+}
+
+/// The outer attributes at the start of an item kept as verbatim tokens, such
+/// as the `#[cfg(..)]` attributes of a `safe fn` item.
+fn verbatim_outer_attrs(tokens: &TokenStream) -> Vec<Attribute> {
+    let parser = |input: ParseStream| {
+        let attrs = input.call(Attribute::parse_outer)?;
+        input.parse::<TokenStream>()?;
+        Ok(attrs)
+    };
+    parser.parse2(tokens.clone()).unwrap_or_default()
+}
+
+pub fn join_spans(start: Span, end: Span) -> Span {
+    start.join(end).unwrap_or(start)
+}
+
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(i) => &i.attrs,
+        Item::Enum(i) => &i.attrs,
+        Item::ExternCrate(i) => &i.attrs,
+        Item::Fn(i) => &i.attrs,
+        Item::ForeignMod(i) => &i.attrs,
+        Item::Impl(i) => &i.attrs,
+        Item::Macro(i) => &i.attrs,
+        Item::Mod(i) => &i.attrs,
+        Item::Static(i) => &i.attrs,
+        Item::Struct(i) => &i.attrs,
+        Item::Trait(i) => &i.attrs,
+        Item::TraitAlias(i) => &i.attrs,
+        Item::Type(i) => &i.attrs,
+        Item::Union(i) => &i.attrs,
+        Item::Use(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+fn impl_item_attrs(impl_item: &ImplItem) -> &[Attribute] {
+    match impl_item {
+        ImplItem::Const(i) => &i.attrs,
+        ImplItem::Fn(i) => &i.attrs,
+        ImplItem::Type(i) => &i.attrs,
+        ImplItem::Macro(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+fn trait_item_attrs(trait_item: &TraitItem) -> &[Attribute] {
+    match trait_item {
+        TraitItem::Const(i) => &i.attrs,
+        TraitItem::Fn(i) => &i.attrs,
+        TraitItem::Type(i) => &i.attrs,
+        TraitItem::Macro(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+fn foreign_item_attrs(foreign_item: &ForeignItem) -> &[Attribute] {
+    match foreign_item {
+        ForeignItem::Fn(i) => &i.attrs,
+        ForeignItem::Static(i) => &i.attrs,
+        ForeignItem::Type(i) => &i.attrs,
+        ForeignItem::Macro(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+fn expr_attrs(expr: &Expr) -> &[Attribute] {
+    match expr {
+        Expr::Array(e) => &e.attrs,
+        Expr::Assign(e) => &e.attrs,
+        Expr::Async(e) => &e.attrs,
+        Expr::Await(e) => &e.attrs,
+        Expr::Binary(e) => &e.attrs,
+        Expr::Block(e) => &e.attrs,
+        Expr::Break(e) => &e.attrs,
+        Expr::Call(e) => &e.attrs,
+        Expr::Cast(e) => &e.attrs,
+        Expr::Closure(e) => &e.attrs,
+        Expr::Const(e) => &e.attrs,
+        Expr::Continue(e) => &e.attrs,
+        Expr::Field(e) => &e.attrs,
+        Expr::ForLoop(e) => &e.attrs,
+        Expr::Group(e) => &e.attrs,
+        Expr::If(e) => &e.attrs,
+        Expr::Index(e) => &e.attrs,
+        Expr::Infer(e) => &e.attrs,
+        Expr::Let(e) => &e.attrs,
+        Expr::Lit(e) => &e.attrs,
+        Expr::Loop(e) => &e.attrs,
+        Expr::Macro(e) => &e.attrs,
+        Expr::Match(e) => &e.attrs,
+        Expr::MethodCall(e) => &e.attrs,
+        Expr::Paren(e) => &e.attrs,
+        Expr::Path(e) => &e.attrs,
+        Expr::Range(e) => &e.attrs,
+        Expr::RawAddr(e) => &e.attrs,
+        Expr::Reference(e) => &e.attrs,
+        Expr::Repeat(e) => &e.attrs,
+        Expr::Return(e) => &e.attrs,
+        Expr::Struct(e) => &e.attrs,
+        Expr::Try(e) => &e.attrs,
+        Expr::TryBlock(e) => &e.attrs,
+        Expr::Tuple(e) => &e.attrs,
+        Expr::Unary(e) => &e.attrs,
+        Expr::Unsafe(e) => &e.attrs,
+        Expr::While(e) => &e.attrs,
+        Expr::Yield(e) => &e.attrs,
+        _ => &[],
+    }
+}
+
+impl<'ast> visit::Visit<'ast> for GeigerSynVisitor {
+    fn visit_file(&mut self, i: &'ast syn::File) {
+        self.metrics.lint_status = file_lint_status(i);
+        self.metrics.forbids_unsafe =
+            self.metrics.lint_status == LintStatus::Forbid;
+        self.metrics.allows_unsafe_locally = file_allows_unsafe_locally(i);
+        self.static_mut_paths = file_static_mut_paths(i);
+        self.unsafe_operation_names = UnsafeOperationNames::from_file(i);
+        // Inner `#![cfg(..)]` attributes apply to the whole file
+        self.visit_with_cfg(&i.attrs, |visitor| {
+            syn::visit::visit_file(visitor, i)
+        });
+    }
+
+    fn visit_item(&mut self, i: &Item) {
+        self.visit_with_cfg(item_attrs(i), |visitor| {
+            visit::visit_item(visitor, i)
+        });
+    }
+
+    fn visit_impl_item(&mut self, i: &ImplItem) {
+        self.visit_with_cfg(impl_item_attrs(i), |visitor| {
+            visit::visit_impl_item(visitor, i)
+        });
+    }
+
+    fn visit_trait_item(&mut self, i: &TraitItem) {
+        self.visit_with_cfg(trait_item_attrs(i), |visitor| {
+            visit::visit_trait_item(visitor, i)
+        });
+    }
+
+    fn visit_foreign_item(&mut self, i: &ForeignItem) {
+        match i {
+            // syn keeps the `safe fn` and `safe static` items of edition 2024
+            // `unsafe extern` blocks as verbatim tokens
+            ForeignItem::Verbatim(tokens) => {
+                let keyword = safe_foreign_item_keyword(tokens);
+                self.visit_with_cfg(&verbatim_outer_attrs(tokens), |visitor| {
+                    match keyword.as_deref() {
+                        Some("fn") => visitor
+                            .metrics
+                            .counters
+                            .foreign_functions
+                            .count(false),
+                        Some("static") => visitor
+                            .metrics
+                            .counters
+                            .foreign_statics
+                            .count(false),
+                        _ => {}
+                    }
+                });
+            }
+            _ => self.visit_with_cfg(foreign_item_attrs(i), |visitor| {
+                visit::visit_foreign_item(visitor, i)
+            }),
+        }
+    }
+
+    fn visit_local(&mut self, i: &Local) {
+        self.visit_with_cfg(&i.attrs, |visitor| visit::visit_local(visitor, i));
+    }
+
+    fn visit_arm(&mut self, i: &Arm) {
+        self.visit_with_cfg(&i.attrs, |visitor| visit::visit_arm(visitor, i));
+    }
+
+    /// Free-standing functions
+    fn visit_item_fn(&mut self, item_fn: &ItemFn) {
+        if IncludeTests::No == self.include_tests && is_test_fn(item_fn) {
             return;
         }
-        visit::visit_item_mod(self, i);
+        let unsafe_fn =
+            item_fn.sig.unsafety.is_some() || has_unsafe_attributes(item_fn);
+        if unsafe_fn {
+            self.record_unsafe_location(UnsafeKind::Function, item_fn.span());
+        }
+        self.metrics.counters.functions.count(unsafe_fn);
+        if is_non_rust_abi(&item_fn.sig.abi) {
+            self.metrics
+                .counters
+                .exported_functions
+                .count(has_unsafe_attributes(item_fn));
+        }
+        // Exported functions are counted as unsafe, but only the body of an
+        // `unsafe fn` is an unsafe scope
+        let mut visitor = self.function_body(item_fn.sig.unsafety.is_some());
+        visitor.visit_function(
+            &item_fn.sig.ident,
+            unsafe_fn,
+            item_fn.span(),
+            |visitor| visit::visit_item_fn(visitor, item_fn),
+        );
+    }
+
+    fn visit_expr(&mut self, i: &Expr) {
+        self.visit_with_cfg(expr_attrs(i), |visitor| visitor.count_expr(i));
+    }
+
+    fn visit_stmt(&mut self, i: &Stmt) {
+        let statement_span = self.statement_span.replace(i.span());
+        visit::visit_stmt(self, i);
+        self.statement_span = statement_span;
+    }
+
+    fn visit_stmt_macro(&mut self, i: &StmtMacro) {
+        self.visit_with_cfg(&i.attrs, |visitor| {
+            visit::visit_stmt_macro(visitor, i)
+        });
+    }
+
+    fn visit_foreign_item_fn(&mut self, i: &ForeignItemFn) {
+        self.metrics.counters.foreign_functions.count(true);
+        visit::visit_foreign_item_fn(self, i);
+    }
+
+    fn visit_foreign_item_static(&mut self, i: &ForeignItemStatic) {
+        self.metrics.counters.foreign_statics.count(true);
+        visit::visit_foreign_item_static(self, i);
+    }
+
+    fn visit_item_static(&mut self, i: &ItemStatic) {
+        self.metrics
+            .counters
+            .statics
+            .count(matches!(i.mutability, StaticMutability::Mut(_)));
+        visit::visit_item_static(self, i);
     }
 
     fn visit_item_impl(&mut self, i: &ItemImpl) {
         // unsafe trait impl's
+        if i.unsafety.is_some() {
+            self.record_unsafe_location(UnsafeKind::Impl, i.span());
+            self.count_safety_comment(i.span());
+        }
         self.metrics.counters.item_impls.count(i.unsafety.is_some());
+        if let Some((_, path, _)) = &i.trait_ {
+            let trait_name = path.segments.last().map(|s| s.ident.to_string());
+            match trait_name.as_deref() {
+                Some("Send") | Some("Sync") => self
+                    .metrics
+                    .counters
+                    .send_sync_impls
+                    .count(i.unsafety.is_some()),
+                Some(name) if MARKER_TRAITS.contains(&name) => self
+                    .metrics
+                    .counters
+                    .marker_trait_impls
+                    .count(i.unsafety.is_some()),
+                _ => {}
+            }
+        }
+        let in_union_impl = mem::replace(
+            &mut self.in_union_impl,
+            self.unsafe_operation_names.is_union_type(&i.self_ty),
+        );
+        let self_type = self.self_type.replace(type_name(&i.self_ty));
+        self.item_path.push(type_name(&i.self_ty));
         visit::visit_item_impl(self, i);
+        self.item_path.pop();
+        self.self_type = self_type;
+        self.in_union_impl = in_union_impl;
+    }
+
+    /// Inline modules, whose names are part of the function paths and of the
+    /// paths of their `static mut` items
+    fn visit_item_mod(&mut self, i: &ItemMod) {
+        self.item_path.push(i.ident.unraw().to_string());
+        self.module_path.push(i.ident.unraw().to_string());
+        visit::visit_item_mod(self, i);
+        self.module_path.pop();
+        self.item_path.pop();
     }
 
     fn visit_item_trait(&mut self, i: &ItemTrait) {
         // Unsafe traits
+        if i.unsafety.is_some() {
+            self.record_unsafe_location(UnsafeKind::Trait, i.span());
+        }
         self.metrics
             .counters
             .item_traits
             .count(i.unsafety.is_some());
+        let self_type = self.self_type.replace(i.ident.unraw().to_string());
         visit::visit_item_trait(self, i);
+        self.self_type = self_type;
     }
 
-    fn visit_impl_item_method(&mut self, i: &ImplItemMethod) {
+    fn visit_impl_item_fn(&mut self, i: &ImplItemFn) {
         if i.sig.unsafety.is_some() {
-            self.enter_unsafe_scope()
+            self.record_unsafe_location(UnsafeKind::Method, i.span());
         }
         self.metrics
             .counters
             .methods
             .count(i.sig.unsafety.is_some());
-        visit::visit_impl_item_method(self, i);
+        let mut visitor = self.function_body(i.sig.unsafety.is_some());
+        visitor.visit_function(
+            &i.sig.ident,
+            i.sig.unsafety.is_some(),
+            i.span(),
+            |visitor| visit::visit_impl_item_fn(visitor, i),
+        );
+    }
+
+    /// Trait methods, both declarations and methods with a default body
+    fn visit_trait_item_fn(&mut self, i: &TraitItemFn) {
         if i.sig.unsafety.is_some() {
-            self.exit_unsafe_scope()
+            self.record_unsafe_location(UnsafeKind::Method, i.span());
+        }
+        self.metrics
+            .counters
+            .methods
+            .count(i.sig.unsafety.is_some());
+        let mut visitor = self.function_body(i.sig.unsafety.is_some());
+        visit::visit_trait_item_fn(&mut *visitor, i);
+    }
+
+    fn visit_expr_call(&mut self, i: &ExprCall) {
+        if let Expr::Path(expr_path) = &*i.func {
+            let rule = self
+                .dangerous_api_rules
+                .match_path(expr_path)
+                .map(str::to_string);
+            self.count_dangerous_api_call(rule);
+        }
+        visit::visit_expr_call(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &ExprMethodCall) {
+        let rule = self
+            .dangerous_api_rules
+            .match_method(&i.method)
+            .map(str::to_string);
+        self.count_dangerous_api_call(rule);
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &Macro) {
+        // `global_asm!` is used outside of unsafe scopes
+        if is_asm_macro_path(&i.path)
+            && (self.unsafe_scopes > 0 || i.path.is_ident("global_asm"))
+        {
+            self.count_unsafe_operation(UnsafeOperation::InlineAsm);
         }
+        self.count_unsafe_in_tokens(i.tokens.clone());
+        visit::visit_macro(self, i);
     }
 
-    // TODO: Visit macros.
-    //
     // TODO: Figure out if there are other visit methods that should be
     // implemented here.
 }
@@ -10,15 +10,33 @@
 pub mod find;
 pub use find::*; // preserve APIs
 
+mod cfg;
+mod dangerous_api;
 mod geiger_syn_visitor;
+mod module_tree;
+mod safety_comments;
+mod scanner;
+mod token_scan;
+mod unsafe_operations;
 
-use cargo_geiger_serde::CounterBlock;
+pub use cfg::CfgSet;
+pub use dangerous_api::DangerousApiRules;
+pub use module_tree::{resolve_module_tree, ModuleTree};
+pub use scanner::{MetricPlugin, Scanner};
+pub use token_scan::find_unsafe_in_tokens;
+
+use cargo_geiger_serde::{
+    CounterBlock, FunctionMetrics, LintStatus, SafetyCommentCoverage,
+    UnsafeLocation,
+};
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::io;
 use std::path::PathBuf;
 use std::string::FromUtf8Error;
-use syn::{ItemFn, ItemMod};
+use syn::ext::IdentExt;
+use syn::{visit, ItemFn, ItemMod, ItemStatic, Path, StaticMutability, Type};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum IncludeTests {
@@ -32,8 +50,44 @@ pub struct RsFileMetrics {
     /// Metrics storage.
     pub counters: CounterBlock,
 
+    /// Metrics storage for the code that is not compiled for the scanned
+    /// target, because of its `#[cfg(..)]` attributes.
+    pub inactive_counters: CounterBlock,
+
     /// This file is decorated with `#![forbid(unsafe_code)]`
     pub forbids_unsafe: bool,
+
+    /// Level of the `unsafe_code` lint declared by the inner attributes of
+    /// this file.
+    pub lint_status: LintStatus,
+
+    /// This file contains an `#[allow(unsafe_code)]`, or a similar attribute
+    /// lowering the lint level, on one of its items.
+    pub allows_unsafe_locally: bool,
+
+    /// Source locations of the unsafe functions, blocks, impls, traits and
+    /// methods, in the order they were found.
+    pub unsafe_locations: Vec<UnsafeLocation>,
+
+    /// Unsafe usage of the free-standing functions and of the methods of
+    /// impl blocks compiled for the target, in the order they were found.
+    pub functions: Vec<FunctionMetrics>,
+
+    /// Coverage of the `unsafe` blocks and `unsafe impl`s compiled for the
+    /// target by `// SAFETY:` comments.
+    pub safety_comments: SafetyCommentCoverage,
+
+    /// This file could not be parsed, the metrics were counted from its
+    /// tokens by `find_unsafe_in_tokens` and are approximate.
+    pub approximate: bool,
+
+    /// The error the file could not be parsed with, when its metrics were
+    /// counted from its tokens by `find_unsafe_in_file`.
+    pub parse_error: Option<String>,
+
+    /// Counts of the metrics of the `MetricPlugin`s of a `Scanner`, keyed by
+    /// plugin name. Empty when the file could not be parsed.
+    pub plugin_metrics: BTreeMap<String, u64>,
 }
 
 #[derive(Debug)]
@@ -53,75 +107,228 @@ impl fmt::Display for ScanFileError {
     }
 }
 
-fn file_forbids_unsafe(f: &syn::File) -> bool {
+/// The level of the `unsafe_code` lint declared by the inner attributes of
+/// the file, such as `#![forbid(unsafe_code)]` or
+/// `#![cfg_attr(not(test), forbid(unsafe_code))]`. The strongest level wins,
+/// since a `forbid` can not be overridden later on.
+fn file_lint_status(f: &syn::File) -> LintStatus {
     use syn::AttrStyle;
-    use syn::Meta;
-    use syn::MetaList;
-    use syn::NestedMeta;
     f.attrs
         .iter()
         .filter(|a| matches!(a.style, AttrStyle::Inner(_)))
-        .filter_map(|a| a.parse_meta().ok())
-        .filter(|meta| match meta {
-            Meta::List(MetaList {
-                path,
-                paren_token: _paren,
-                nested,
-            }) => {
-                if !path.is_ident("forbid") {
-                    return false;
-                }
-                nested.iter().any(|n| match n {
-                    NestedMeta::Meta(Meta::Path(p)) => {
-                        p.is_ident("unsafe_code")
-                    }
-                    _ => false,
-                })
-            }
-            _ => false,
+        .filter_map(|a| meta_unsafe_code_lint_status(&a.meta, false))
+        .max()
+        .unwrap_or_default()
+}
+
+/// Whether any attribute in the file, other than the inner attributes of the
+/// file itself, lowers the level of the `unsafe_code` lint to `allow`,
+/// `warn` or `expect`.
+fn file_allows_unsafe_locally(f: &syn::File) -> bool {
+    struct LocalAllowVisitor(bool);
+
+    impl<'ast> visit::Visit<'ast> for LocalAllowVisitor {
+        fn visit_attribute(&mut self, i: &'ast syn::Attribute) {
+            self.0 |= meta_unsafe_code_lint_status(&i.meta, false)
+                == Some(LintStatus::Allow);
+        }
+    }
+
+    let mut visitor = LocalAllowVisitor(false);
+    for item in &f.items {
+        visit::Visit::visit_item(&mut visitor, item);
+    }
+    visitor.0
+}
+
+/// The level of the `unsafe_code` lint set by a single attribute, if any.
+/// Lint lists with several lints are supported, and the attributes inside a
+/// `cfg_attr` are considered with their `forbid` or `deny` level made
+/// conditional.
+fn meta_unsafe_code_lint_status(
+    meta: &syn::Meta,
+    conditional: bool,
+) -> Option<LintStatus> {
+    use syn::Meta;
+    let meta_list = match meta {
+        Meta::List(meta_list) => meta_list,
+        _ => return None,
+    };
+    let nested = meta_list_nested(meta_list);
+    if meta_list.path.is_ident("cfg_attr") {
+        // The first nested meta is the configuration predicate
+        return nested
+            .iter()
+            .skip(1)
+            .filter_map(|meta| meta_unsafe_code_lint_status(meta, true))
+            .max();
+    }
+    let names_unsafe_code = nested
+        .iter()
+        .any(|meta| meta_contains_ident(meta, "unsafe_code"));
+    if !names_unsafe_code {
+        return None;
+    }
+    let path = &meta_list.path;
+    if path.is_ident("forbid") {
+        Some(if conditional {
+            LintStatus::ConditionalForbid
+        } else {
+            LintStatus::Forbid
         })
-        .count()
-        > 0
+    } else if path.is_ident("deny") {
+        Some(if conditional {
+            LintStatus::ConditionalDeny
+        } else {
+            LintStatus::Deny
+        })
+    } else if path.is_ident("allow")
+        || path.is_ident("warn")
+        || path.is_ident("expect")
+    {
+        Some(LintStatus::Allow)
+    } else {
+        None
+    }
 }
 
-fn is_test_fn(item_fn: &ItemFn) -> bool {
-    use syn::Attribute;
-    item_fn
-        .attrs
+/// Paths of all `static mut` items declared in the file, made of the names
+/// of the inline modules enclosing them and their own name. The items
+/// declared inside function bodies are included, under the path of the module
+/// enclosing the function.
+fn file_static_mut_paths(f: &syn::File) -> HashSet<Vec<String>> {
+    #[derive(Default)]
+    struct StaticMutVisitor {
+        module_path: Vec<String>,
+        paths: HashSet<Vec<String>>,
+    }
+
+    impl<'ast> visit::Visit<'ast> for StaticMutVisitor {
+        fn visit_item_mod(&mut self, i: &'ast ItemMod) {
+            self.module_path.push(i.ident.unraw().to_string());
+            visit::visit_item_mod(self, i);
+            self.module_path.pop();
+        }
+
+        fn visit_item_static(&mut self, i: &'ast ItemStatic) {
+            if matches!(i.mutability, StaticMutability::Mut(_)) {
+                let mut path = self.module_path.clone();
+                path.push(i.ident.unraw().to_string());
+                self.paths.insert(path);
+            }
+            visit::visit_item_static(self, i);
+        }
+    }
+
+    let mut visitor = StaticMutVisitor::default();
+    visit::Visit::visit_file(&mut visitor, f);
+    visitor.paths
+}
+
+/// The segments of a path, without the `r#` prefix of raw identifiers.
+fn path_segments(path: &Path) -> Vec<String> {
+    path.segments
         .iter()
-        .flat_map(Attribute::parse_meta)
-        .any(|m| meta_contains_ident(&m, "test"))
+        .map(|segment| segment.ident.unraw().to_string())
+        .collect()
 }
 
-fn has_unsafe_attributes(item_fn: &ItemFn) -> bool {
-    use syn::Attribute;
+/// Whether a path used in the inline module at `module_path` refers to one
+/// of `declared_paths`, the paths of items declared in the file. A single name
+/// matches any item of that name. Paths leaving the file through `crate` or
+/// `super` match the items they end with, since the path of the file in its
+/// crate is not known.
+fn resolves_to_declared_path(
+    declared_paths: &HashSet<Vec<String>>,
+    module_path: &[String],
+    segments: &[String],
+) -> bool {
+    if let [name] = segments {
+        return declared_paths
+            .iter()
+            .any(|declared_path| declared_path.last() == Some(name));
+    }
+    let ends_with_declared_path = |segments: &[String]| {
+        declared_paths
+            .iter()
+            .any(|declared_path| segments.ends_with(declared_path))
+    };
+    let (module_path, relative_segments) = match segments.first() {
+        Some(first) if first == "crate" => {
+            return ends_with_declared_path(&segments[1..])
+        }
+        Some(first) if first == "self" => (module_path, &segments[1..]),
+        Some(first) if first == "super" => {
+            let supers = segments
+                .iter()
+                .take_while(|segment| *segment == "super")
+                .count();
+            if supers > module_path.len() {
+                return ends_with_declared_path(&segments[supers..]);
+            }
+            (
+                &module_path[..module_path.len() - supers],
+                &segments[supers..],
+            )
+        }
+        Some(_) => (module_path, segments),
+        None => return false,
+    };
+    let mut resolved_path = module_path.to_vec();
+    resolved_path.extend_from_slice(relative_segments);
+    declared_paths.contains(&resolved_path)
+}
+
+/// The name of the type of an impl block, such as `Vec` for `Vec<T>` or
+/// `&'a Vec<T>`, or `_` for other kinds of types.
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.unraw().to_string())
+            .unwrap_or_else(|| String::from("_")),
+        Type::Group(type_group) => type_name(&type_group.elem),
+        Type::Paren(type_paren) => type_name(&type_paren.elem),
+        Type::Ptr(type_ptr) => type_name(&type_ptr.elem),
+        Type::Reference(type_reference) => type_name(&type_reference.elem),
+        _ => String::from("_"),
+    }
+}
+
+fn is_test_fn(item_fn: &ItemFn) -> bool {
     item_fn
         .attrs
         .iter()
-        .flat_map(Attribute::parse_meta)
-        .any(|m| {
-            meta_contains_ident(&m, "no_mangle")
-                || meta_contains_attribute(&m, "export_name")
-        })
+        .any(|a| meta_contains_ident(&a.meta, "test"))
 }
 
-/// Will return true for #[cfg(test)] decorated modules.
-///
-/// This function is a somewhat of a hack and will probably misinterpret more
-/// advanced cfg expressions. A better way to do this would be to let rustc emit
-/// every single source file path and span within each source file and use that
-/// as a general filter for included code.
-/// TODO: Investigate if the needed information can be emitted by rustc today.
-fn is_test_mod(i: &ItemMod) -> bool {
-    use syn::Attribute;
+/// Whether the function is exported with `#[no_mangle]` or `#[export_name]`,
+/// also when written in the `#[unsafe(..)]` form required by edition 2024.
+fn has_unsafe_attributes(item_fn: &ItemFn) -> bool {
     use syn::Meta;
-    i.attrs
-        .iter()
-        .flat_map(Attribute::parse_meta)
-        .any(|m| match m {
-            Meta::List(ml) => meta_list_is_cfg_test(&ml),
-            _ => false,
-        })
+    let is_unsafe_attribute = |m: &Meta| {
+        meta_contains_ident(m, "no_mangle")
+            || meta_contains_attribute(m, "export_name")
+    };
+    item_fn.attrs.iter().any(|a| match &a.meta {
+        Meta::List(meta_list) if meta_list.path.is_ident("unsafe") => {
+            meta_list_nested(meta_list).iter().any(is_unsafe_attribute)
+        }
+        meta => is_unsafe_attribute(meta),
+    })
+}
+
+/// The comma separated metas inside the parentheses of an attribute such as
+/// `#[cfg_attr(unix, forbid(unsafe_code))]`. Empty if they are not metas.
+fn meta_list_nested(meta_list: &syn::MetaList) -> Vec<syn::Meta> {
+    use syn::punctuated::Punctuated;
+    use syn::{Meta, Token};
+    meta_list
+        .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+        .map(|nested| nested.into_iter().collect())
+        .unwrap_or_default()
 }
 
 fn meta_contains_ident(m: &syn::Meta, ident: &str) -> bool {
@@ -139,29 +346,3 @@ fn meta_contains_attribute(m: &syn::Meta, ident: &str) -> bool {
         _ => false,
     }
 }
-
-// MetaList {
-//     ident: Ident(
-//         cfg
-//     ),
-//     paren_token: Paren,
-//     nested: [
-//         Meta(
-//             Word(
-//                 Ident(
-//                     test
-//                 )
-//             )
-//         )
-//     ]
-// }
-fn meta_list_is_cfg_test(meta_list: &syn::MetaList) -> bool {
-    use syn::NestedMeta;
-    if !meta_list.path.is_ident("cfg") {
-        return false;
-    }
-    meta_list.nested.iter().any(|n| match n {
-        NestedMeta::Meta(meta) => meta_contains_ident(meta, "test"),
-        _ => false,
-    })
-}
@@ -0,0 +1,329 @@
+use super::{meta_list_nested, CfgSet, ScanFileError};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::ext::IdentExt;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprLit, Item, Lit, LitStr, Macro, Meta};
+
+/// The source files of a crate target.
+#[derive(Debug, Default)]
+pub struct ModuleTree {
+    /// Canonical paths of the entry point, of the files of its modules and of
+    /// the files included with `include!`.
+    pub files: HashSet<PathBuf>,
+
+    /// Files that could not be found, read or parsed. The modules declared in
+    /// them are missing from `files`.
+    pub errors: Vec<ScanFileError>,
+}
+
+/// Resolve the module tree of a crate target from its entry point, such as
+/// `src/lib.rs`, the way rustc does, without building anything. Files are
+/// found by following `mod foo;` declarations, `#[path = ".."]` attributes,
+/// nested module directories and `include!` macros with a literal path.
+/// Modules excluded by `#[cfg(..)]` attributes that evaluate to false for
+/// `cfg_set` are not followed, and neither are paths computed by macros such
+/// as `include!(concat!(env!("OUT_DIR"), "/generated.rs"))`.
+pub fn resolve_module_tree(entry_point: &Path, cfg_set: &CfgSet) -> ModuleTree {
+    let mut resolver = ModuleResolver {
+        cfg_set,
+        tree: ModuleTree::default(),
+    };
+    let mod_dir = entry_point.parent().unwrap_or(Path::new("")).to_path_buf();
+    resolver.add_file(entry_point, &mod_dir);
+    resolver.tree
+}
+
+struct ModuleResolver<'a> {
+    cfg_set: &'a CfgSet,
+    tree: ModuleTree,
+}
+
+impl ModuleResolver<'_> {
+    /// Add a file and the files of its modules, which are looked up in
+    /// `mod_dir`.
+    fn add_file(&mut self, path: &Path, mod_dir: &Path) {
+        let file = match self.read_file(path) {
+            Some(file) => file,
+            None => return,
+        };
+        let file_dir = path.parent().unwrap_or(Path::new(""));
+        self.add_items(&file.items, mod_dir, file_dir);
+        self.add_included_files(&file, file_dir, mod_dir);
+    }
+
+    /// Canonicalize, record, read and parse a file. Nothing is returned for
+    /// a file that was already added, which also protects against cycles.
+    fn read_file(&mut self, path: &Path) -> Option<syn::File> {
+        let canonical_path = match path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
+            Err(e) => {
+                self.tree
+                    .errors
+                    .push(ScanFileError::Io(e, path.to_path_buf()));
+                return None;
+            }
+        };
+        if !self.tree.files.insert(canonical_path) {
+            return None;
+        }
+        let src = match fs::read(path) {
+            Ok(src) => src,
+            Err(e) => {
+                self.tree
+                    .errors
+                    .push(ScanFileError::Io(e, path.to_path_buf()));
+                return None;
+            }
+        };
+        let src = match String::from_utf8(src) {
+            Ok(src) => src,
+            Err(e) => {
+                self.tree
+                    .errors
+                    .push(ScanFileError::Utf8(e, path.to_path_buf()));
+                return None;
+            }
+        };
+        match syn::parse_file(&src) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                self.tree
+                    .errors
+                    .push(ScanFileError::Syn(e, path.to_path_buf()));
+                None
+            }
+        }
+    }
+
+    /// Add the modules declared in `items`. `mod foo;` is looked up in
+    /// `mod_dir`, and `#[path = ".."]` attributes are relative to
+    /// `path_attr_dir`, which only differs from `mod_dir` at the top level of
+    /// a file that is neither an entry point nor a `mod.rs` file.
+    fn add_items(
+        &mut self,
+        items: &[Item],
+        mod_dir: &Path,
+        path_attr_dir: &Path,
+    ) {
+        for item in items {
+            let item_mod = match item {
+                Item::Mod(item_mod) => item_mod,
+                _ => continue,
+            };
+            if self.cfg_set.evaluate_attributes(&item_mod.attrs) == Some(false)
+            {
+                continue;
+            }
+            let name = item_mod.ident.unraw().to_string();
+            let path_attr = path_attribute(&item_mod.attrs, self.cfg_set);
+            match (&item_mod.content, path_attr) {
+                (Some((_, items)), Some(path_attr)) => {
+                    let dir = path_attr_dir.join(path_attr);
+                    self.add_items(items, &dir, &dir);
+                }
+                (Some((_, items)), None) => {
+                    let dir = mod_dir.join(&name);
+                    self.add_items(items, &dir, &dir);
+                }
+                // Files loaded through a path attribute own their directory,
+                // like `mod.rs` files
+                (None, Some(path_attr)) => {
+                    let path = path_attr_dir.join(path_attr);
+                    let dir =
+                        path.parent().unwrap_or(Path::new("")).to_path_buf();
+                    self.add_file(&path, &dir);
+                }
+                (None, None) => {
+                    let dir = mod_dir.join(&name);
+                    let file_path = mod_dir.join(format!("{}.rs", name));
+                    let mod_rs_path = dir.join("mod.rs");
+                    if !file_path.is_file() && mod_rs_path.is_file() {
+                        self.add_file(&mod_rs_path, &dir);
+                    } else {
+                        self.add_file(&file_path, &dir);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add the files included with `include!("..")`, relative to the
+    /// directory of the including file. The included items belong to the
+    /// module of the including file.
+    fn add_included_files(
+        &mut self,
+        file: &syn::File,
+        file_dir: &Path,
+        mod_dir: &Path,
+    ) {
+        let mut collector = IncludeCollector::default();
+        collector.visit_file(file);
+        for include in collector.paths {
+            let path = file_dir.join(include);
+            // Included files are often expressions rather than items, so
+            // they are only followed when they parse as a file
+            if let Some(file) = self.read_included_file(&path) {
+                self.add_items(&file.items, mod_dir, file_dir);
+            }
+        }
+    }
+
+    fn read_included_file(&mut self, path: &Path) -> Option<syn::File> {
+        let error_count = self.tree.errors.len();
+        let file = self.read_file(path);
+        if let Some(ScanFileError::Syn(..)) = self.tree.errors.get(error_count)
+        {
+            self.tree.errors.truncate(error_count);
+        }
+        file
+    }
+}
+
+/// The path of a `#[path = ".."]` attribute, or of a
+/// `#[cfg_attr(.., path = "..")]` attribute whose predicate holds.
+fn path_attribute(
+    attrs: &[syn::Attribute],
+    cfg_set: &CfgSet,
+) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        Meta::NameValue(_) => path_meta_value(&attr.meta),
+        Meta::List(meta_list) if meta_list.path.is_ident("cfg_attr") => {
+            let nested = meta_list_nested(meta_list);
+            let (predicate, metas) = nested.split_first()?;
+            if cfg_set.evaluate(predicate) != Some(true) {
+                return None;
+            }
+            metas.iter().find_map(path_meta_value)
+        }
+        _ => None,
+    })
+}
+
+fn path_meta_value(meta: &Meta) -> Option<String> {
+    match meta {
+        Meta::NameValue(name_value) if name_value.path.is_ident("path") => {
+            match &name_value.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(value),
+                    ..
+                }) => Some(value.value()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Collects the literal paths of the `include!` macros of a file.
+#[derive(Default)]
+struct IncludeCollector {
+    paths: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for IncludeCollector {
+    fn visit_macro(&mut self, i: &'ast Macro) {
+        if i.path.is_ident("include") {
+            if let Ok(path) = i.parse_body::<LitStr>() {
+                self.paths.push(path.value());
+            }
+        }
+        visit::visit_macro(self, i);
+    }
+}
+
+#[cfg(test)]
+mod module_tree_tests {
+    use super::*;
+
+    use rstest::*;
+    use std::io;
+    use tempfile::tempdir;
+
+    #[rstest]
+    fn resolve_module_tree_test() {
+        let temp_dir = tempdir().unwrap();
+        let files = [
+            (
+                "src/lib.rs",
+                "mod a;
+mod b;
+#[path = \"other/c.rs\"]
+mod c;
+mod inline {
+    mod d;
+}
+#[cfg(any())]
+mod e;
+#[cfg_attr(all(), path = \"f_all.rs\")]
+mod f;
+include!(\"inc.rs\");
+mod r#g;",
+            ),
+            ("src/a.rs", "mod a1;\n#[path = \"sibling.rs\"]\nmod a2;"),
+            ("src/a/a1.rs", ""),
+            ("src/sibling.rs", ""),
+            ("src/b/mod.rs", "mod b1;"),
+            ("src/b/b1.rs", ""),
+            ("src/other/c.rs", "mod c1;"),
+            ("src/other/c1.rs", ""),
+            ("src/inline/d.rs", ""),
+            ("src/e.rs", ""),
+            ("src/f_all.rs", ""),
+            ("src/inc.rs", "mod h;"),
+            ("src/h.rs", ""),
+            ("src/g.rs", "fn f() -> u8 { include!(\"expr.rs\") }"),
+            ("src/expr.rs", "1 + 1"),
+            ("src/unused.rs", ""),
+        ];
+        for (path, content) in files.iter() {
+            let path = temp_dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let entry_point = temp_dir.path().join("src/lib.rs");
+
+        let module_tree = resolve_module_tree(&entry_point, &CfgSet::new());
+
+        let expected_files = [
+            "src/lib.rs",
+            "src/a.rs",
+            "src/a/a1.rs",
+            "src/sibling.rs",
+            "src/b/mod.rs",
+            "src/b/b1.rs",
+            "src/other/c.rs",
+            "src/other/c1.rs",
+            "src/inline/d.rs",
+            "src/f_all.rs",
+            "src/inc.rs",
+            "src/h.rs",
+            "src/g.rs",
+            "src/expr.rs",
+        ]
+        .iter()
+        .map(|path| temp_dir.path().join(path).canonicalize().unwrap())
+        .collect::<HashSet<_>>();
+        assert_eq!(module_tree.files, expected_files);
+        assert!(module_tree.errors.is_empty());
+    }
+
+    #[rstest]
+    fn resolve_module_tree_test_missing_module() {
+        let temp_dir = tempdir().unwrap();
+        let entry_point = temp_dir.path().join("main.rs");
+        fs::write(&entry_point, "mod missing;").unwrap();
+
+        let module_tree = resolve_module_tree(&entry_point, &CfgSet::new());
+
+        assert_eq!(module_tree.files.len(), 1);
+        assert!(matches!(
+            &module_tree.errors[..],
+            [ScanFileError::Io(e, path)]
+                if e.kind() == io::ErrorKind::NotFound
+                    && path == &temp_dir.path().join("missing.rs")
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+use proc_macro2::LineColumn;
+
+/// The lines of a source file, used to find the `// SAFETY:` comments that
+/// document `unsafe` blocks and `unsafe impl`s. Like Clippy's
+/// `undocumented_unsafe_blocks` lint, a comment documents the code that
+/// directly follows it, on the same line or on the next lines with only
+/// comments and attributes in between.
+#[derive(Debug, Default)]
+pub struct SafetyComments {
+    lines: Vec<SourceLine>,
+}
+
+impl SafetyComments {
+    pub fn from_source(src: &str) -> Self {
+        SafetyComments {
+            lines: lex_lines(src),
+        }
+    }
+
+    /// Whether the code starting at `start`, as given by the span of a
+    /// syntax node, is preceded by a `// SAFETY:` comment.
+    pub fn documents(&self, start: LineColumn) -> bool {
+        let index = match start.line.checked_sub(1) {
+            Some(index) if index < self.lines.len() => index,
+            _ => return false,
+        };
+        let comment_before_start =
+            self.lines[index].text(CharKind::Comment, start.column);
+        if has_safety_marker(&comment_before_start) {
+            return true;
+        }
+        for line in self.lines[..index].iter().rev() {
+            let code = line.code();
+            let code = code.trim();
+            if code.is_empty() {
+                let comment = line.text(CharKind::Comment, usize::MAX);
+                if comment.trim().is_empty() && !line.starts_in_comment {
+                    return false;
+                }
+                if has_safety_marker(&comment) {
+                    return true;
+                }
+            } else if !code.starts_with("#[") {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+/// What a character of the source is part of.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CharKind {
+    Code,
+    Comment,
+    /// String, byte string and character literals
+    Literal,
+}
+
+#[derive(Debug, Default)]
+struct SourceLine {
+    chars: Vec<(char, CharKind)>,
+    /// Whether the line starts inside a block comment, so that it is part of
+    /// the comment even when it is blank.
+    starts_in_comment: bool,
+}
+
+impl SourceLine {
+    /// The characters of a kind among the first `columns` of the line.
+    fn text(&self, kind: CharKind, columns: usize) -> String {
+        self.chars
+            .iter()
+            .take(columns)
+            .filter(|(_, k)| *k == kind)
+            .map(|(c, _)| c)
+            .collect()
+    }
+
+    /// The line without its comments.
+    fn code(&self) -> String {
+        self.chars
+            .iter()
+            .filter(|(_, k)| *k != CharKind::Comment)
+            .map(|(c, _)| c)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum LexState {
+    Code,
+    LineComment,
+    /// Nesting depth of the block comment
+    BlockComment(usize),
+    Str,
+    /// Number of `#` of the raw string
+    RawStr(usize),
+}
+
+/// Splits the source into lines of characters classified as code, comments
+/// and literals, so that comment markers inside literals and code lines
+/// starting with `*` are not taken for comments.
+fn lex_lines(src: &str) -> Vec<SourceLine> {
+    let chars = src.chars().collect::<Vec<_>>();
+    let mut lines = vec![SourceLine::default()];
+    let mut state = LexState::Code;
+    let mut i = 0;
+    while i < chars.len() {
+        let rest = &chars[i..];
+        let (len, kind, next_state) = match state {
+            LexState::Code => {
+                lex_code(rest, i > 0 && is_ident_char(chars[i - 1]))
+            }
+            LexState::LineComment if rest[0] == '\n' => {
+                (1, CharKind::Code, LexState::Code)
+            }
+            LexState::LineComment => (1, CharKind::Comment, state),
+            LexState::BlockComment(depth) => match rest {
+                ['/', '*', ..] => {
+                    (2, CharKind::Comment, LexState::BlockComment(depth + 1))
+                }
+                ['*', '/', ..] if depth == 1 => {
+                    (2, CharKind::Comment, LexState::Code)
+                }
+                ['*', '/', ..] => {
+                    (2, CharKind::Comment, LexState::BlockComment(depth - 1))
+                }
+                _ => (1, CharKind::Comment, state),
+            },
+            LexState::Str => match rest {
+                ['\\', _, ..] => (2, CharKind::Literal, state),
+                ['"', ..] => (1, CharKind::Literal, LexState::Code),
+                _ => (1, CharKind::Literal, state),
+            },
+            LexState::RawStr(hashes) => {
+                if rest[0] == '"'
+                    && rest.len() > hashes
+                    && rest[1..=hashes].iter().all(|c| *c == '#')
+                {
+                    (1 + hashes, CharKind::Literal, LexState::Code)
+                } else {
+                    (1, CharKind::Literal, state)
+                }
+            }
+        };
+        state = next_state;
+        for c in &rest[..len.min(rest.len())] {
+            if *c == '\n' {
+                lines.push(SourceLine {
+                    chars: Vec::new(),
+                    starts_in_comment: matches!(
+                        state,
+                        LexState::BlockComment(_)
+                    ),
+                });
+            } else if let Some(line) = lines.last_mut() {
+                line.chars.push((*c, kind));
+            }
+        }
+        i += len;
+    }
+    lines
+}
+
+/// Lexes the token at the start of `rest`, in code. `after_ident` tells
+/// whether the previous character is part of an identifier, which is not the
+/// case before the prefix of a raw string.
+fn lex_code(rest: &[char], after_ident: bool) -> (usize, CharKind, LexState) {
+    match rest {
+        ['/', '/', ..] => (2, CharKind::Comment, LexState::LineComment),
+        ['/', '*', ..] => (2, CharKind::Comment, LexState::BlockComment(1)),
+        ['"', ..] => (1, CharKind::Literal, LexState::Str),
+        // Character literals such as '"' and '\'', but not lifetimes
+        ['\'', '\\', ..] => {
+            let len = rest
+                .get(3..)
+                .unwrap_or_default()
+                .iter()
+                .take_while(|c| **c != '\'' && **c != '\n')
+                .count()
+                + 4;
+            (len.min(rest.len()), CharKind::Literal, LexState::Code)
+        }
+        ['\'', _, '\'', ..] => (3, CharKind::Literal, LexState::Code),
+        _ if !after_ident => match raw_string_prefix(rest) {
+            Some((len, hashes)) => {
+                (len, CharKind::Literal, LexState::RawStr(hashes))
+            }
+            None => (1, CharKind::Code, LexState::Code),
+        },
+        _ => (1, CharKind::Code, LexState::Code),
+    }
+}
+
+/// The length of the prefix of a raw string such as `r#"` or `br"`, and its
+/// number of `#`.
+fn raw_string_prefix(rest: &[char]) -> Option<(usize, usize)> {
+    let r = match rest {
+        ['b' | 'c', 'r', ..] => 1,
+        ['r', ..] => 0,
+        _ => return None,
+    };
+    let hashes = rest[r + 1..].iter().take_while(|c| **c == '#').count();
+    (rest.get(r + 1 + hashes) == Some(&'"')).then_some((r + 2 + hashes, hashes))
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn has_safety_marker(comment: &str) -> bool {
+    comment.to_ascii_uppercase().contains("SAFETY:")
+}
+
+#[cfg(test)]
+mod safety_comments_tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(
+        input_src,
+        input_start,
+        expected_documented,
+        case("unsafe {}", (1, 0), false),
+        case("// SAFETY: no-op\nunsafe {}", (2, 0), true),
+        case("// Safety: no-op\nunsafe {}", (2, 0), true),
+        case("// no-op\nunsafe {}", (2, 0), false),
+        case("// SAFETY: no-op\n\nunsafe {}", (3, 0), false),
+        case("// SAFETY: no-op\nf();\nunsafe {}", (3, 0), false),
+        case(
+            "// SAFETY: the pointer is valid,\n// see `new`.\nunsafe {}",
+            (3, 0),
+            true
+        ),
+        case("/* SAFETY:\n * no-op\n */\nunsafe {}", (4, 0), true),
+        case("// SAFETY: Send\n#[cfg(unix)]\nunsafe impl Send for A {}", (3, 0), true),
+        case("let x = /* SAFETY: no-op */ unsafe {};", (1, 28), true),
+        case("let x = unsafe {}; // SAFETY: no-op", (1, 8), false),
+        case("let s = \"//\"; unsafe {}", (1, 14), false),
+        case("// SAFETY: no-op\n*p = 1;\nunsafe {}", (3, 0), false),
+        case("let s = \"// SAFETY: no-op\"; unsafe {}", (1, 28), false),
+        case("let s = \"\n// SAFETY: no-op\";\nunsafe {}", (3, 0), false),
+        case("let s = r#\"\"\n// SAFETY: no-op\"#;\nunsafe {}", (3, 0), false),
+        case("let c = '\"';\n// SAFETY: no-op\nunsafe {}", (3, 0), true),
+        case("let c = '\\", (1, 0), false),
+        case("/* SAFETY: no-op /* nested */\n\n */\nunsafe {}", (4, 0), true)
+    )]
+    fn documents_test(
+        input_src: &str,
+        input_start: (usize, usize),
+        expected_documented: bool,
+    ) {
+        let safety_comments = SafetyComments::from_source(input_src);
+        let (line, column) = input_start;
+
+        assert_eq!(
+            safety_comments.documents(LineColumn { line, column }),
+            expected_documented
+        );
+    }
+}
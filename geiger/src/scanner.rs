@@ -0,0 +1,202 @@
+use super::find::{scan_file, scan_string};
+use super::{
+    CfgSet, DangerousApiRules, IncludeTests, RsFileMetrics, ScanFileError,
+};
+
+use std::path::Path;
+
+/// A custom metric counted in every file scanned by a `Scanner`, such as the
+/// uses of a project specific macro. The count is usually computed by walking
+/// the file with a `syn::visit::Visit` implementation.
+pub trait MetricPlugin: Send + Sync {
+    /// Key of the metric in `RsFileMetrics::plugin_metrics`, and in the
+    /// `plugin_metrics` of the cargo-geiger Json report.
+    fn name(&self) -> &str;
+
+    /// Count the metric in a parsed file. The whole file is given, including
+    /// the tests and the code excluded by `#[cfg(..)]` attributes.
+    fn count(&self, file: &syn::File) -> u64;
+}
+
+/// Builder for scanning files with custom settings, and with `MetricPlugin`s
+/// counted next to the built-in metrics. A default `Scanner` scans like
+/// `find_unsafe_in_file` with `IncludeTests::No`, no cfgs and no dangerous
+/// API rules.
+pub struct Scanner {
+    include_tests: IncludeTests,
+    cfg_set: CfgSet,
+    dangerous_api_rules: DangerousApiRules,
+    plugins: Vec<Box<dyn MetricPlugin>>,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Scanner {
+            include_tests: IncludeTests::No,
+            cfg_set: CfgSet::default(),
+            dangerous_api_rules: DangerousApiRules::default(),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_tests(mut self, include_tests: IncludeTests) -> Self {
+        self.include_tests = include_tests;
+        self
+    }
+
+    /// The cfgs of the scanned target, see `find_unsafe_in_file`.
+    pub fn cfg_set(mut self, cfg_set: CfgSet) -> Self {
+        self.cfg_set = cfg_set;
+        self
+    }
+
+    pub fn dangerous_api_rules(
+        mut self,
+        dangerous_api_rules: DangerousApiRules,
+    ) -> Self {
+        self.dangerous_api_rules = dangerous_api_rules;
+        self
+    }
+
+    /// Adds a plugin. A plugin replaces an earlier plugin with the same name.
+    pub fn plugin<P: MetricPlugin + 'static>(mut self, plugin: P) -> Self {
+        self.plugins.retain(|p| p.name() != plugin.name());
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Scan a single file, like `find_unsafe_in_file`.
+    pub fn scan_file(
+        &self,
+        path: &Path,
+    ) -> Result<RsFileMetrics, ScanFileError> {
+        scan_file(
+            path,
+            self.include_tests,
+            &self.cfg_set,
+            &self.dangerous_api_rules,
+            &self.plugins,
+        )
+    }
+
+    /// Scan a string of Rust source code, like `find_unsafe_in_string`.
+    pub fn scan_string(&self, src: &str) -> Result<RsFileMetrics, syn::Error> {
+        scan_string(
+            src,
+            self.include_tests,
+            &self.cfg_set,
+            &self.dangerous_api_rules,
+            &self.plugins,
+        )
+    }
+}
+
+#[cfg(test)]
+mod scanner_tests {
+    use super::*;
+
+    use crate::find_unsafe_in_string;
+
+    use rstest::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use syn::visit::{self, Visit};
+    use tempfile::tempdir;
+
+    const SRC: &str = "
+        macro_rules! unsafe_assert { ($e:expr) => { assert!(unsafe { $e }) } }
+
+        fn f(p: *const bool) {
+            unsafe_assert!(*p);
+            unsafe_assert!(!*p);
+        }
+
+        #[test]
+        fn t() {
+            unsafe_assert!(true);
+            unsafe { std::mem::transmute::<u8, i8>(0) };
+        }
+    ";
+
+    struct MacroCounter(&'static str);
+
+    impl MetricPlugin for MacroCounter {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn count(&self, file: &syn::File) -> u64 {
+            struct Visitor<'a>(&'a str, u64);
+
+            impl<'ast> Visit<'ast> for Visitor<'_> {
+                fn visit_macro(&mut self, i: &'ast syn::Macro) {
+                    if i.path.is_ident(self.0) {
+                        self.1 += 1;
+                    }
+                    visit::visit_macro(self, i);
+                }
+            }
+
+            let mut visitor = Visitor(self.0, 0);
+            visitor.visit_file(file);
+            visitor.1
+        }
+    }
+
+    #[rstest]
+    fn scan_string_test_default_scanner_matches_find_unsafe_in_string() {
+        let metrics = Scanner::new().scan_string(SRC).unwrap();
+
+        let expected_metrics = find_unsafe_in_string(
+            SRC,
+            IncludeTests::No,
+            &CfgSet::new(),
+            &DangerousApiRules::new(),
+        )
+        .unwrap();
+        assert_eq!(metrics, expected_metrics);
+        assert!(metrics.plugin_metrics.is_empty());
+    }
+
+    #[rstest]
+    fn scan_string_test_plugins() {
+        let scanner = Scanner::new()
+            .include_tests(IncludeTests::Yes)
+            .dangerous_api_rules(DangerousApiRules::builtin())
+            .plugin(MacroCounter("unsafe_assert"))
+            .plugin(MacroCounter("macro_rules"));
+
+        let metrics = scanner.scan_string(SRC).unwrap();
+
+        assert_eq!(metrics.counters.exprs.unsafe_, 1);
+        assert_eq!(metrics.counters.dangerous_api_calls["mem::transmute"], 1);
+        let expected_plugin_metrics = vec![
+            (String::from("macro_rules"), 1),
+            (String::from("unsafe_assert"), 3),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+        assert_eq!(metrics.plugin_metrics, expected_plugin_metrics);
+    }
+
+    #[rstest]
+    fn scan_file_test_plugins() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(&path, SRC).unwrap();
+        let scanner = Scanner::new()
+            .plugin(MacroCounter("unsafe_assert"))
+            .plugin(MacroCounter("unsafe_assert"));
+
+        let metrics = scanner.scan_file(&path).unwrap();
+
+        assert_eq!(metrics.plugin_metrics.len(), 1);
+        assert_eq!(metrics.plugin_metrics["unsafe_assert"], 3);
+    }
+}
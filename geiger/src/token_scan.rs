@@ -0,0 +1,407 @@
+use super::{
+    meta_contains_ident, meta_list_nested, meta_unsafe_code_lint_status,
+    IncludeTests, RsFileMetrics,
+};
+
+use crate::geiger_syn_visitor::{
+    find_fn_body_span, find_unsafe_fn_qualifier, is_fn_name, join_spans,
+};
+
+use cargo_geiger_serde::{LineColumn, LintStatus, UnsafeKind, UnsafeLocation};
+use proc_macro2::{Delimiter, LexError, Span, TokenStream, TokenTree};
+
+/// Scan a string of Rust source code that syn can not parse, for example
+/// because it uses syntax that is not supported yet. The source is only
+/// tokenized, and functions, methods, impls, traits and `unsafe` blocks are
+/// recognized from the keywords around them, so the returned metrics are
+/// marked as approximate. Each `unsafe` block counts as a single unsafe
+/// expression, and `#[cfg(..)]` attributes are ignored, except for
+/// `#[cfg(test)]` when tests are not included.
+pub fn find_unsafe_in_tokens(
+    src: &str,
+    include_tests: IncludeTests,
+) -> Result<RsFileMetrics, LexError> {
+    let tokens = src.parse::<TokenStream>()?.into_iter().collect::<Vec<_>>();
+    let mut scanner = TokenScanner {
+        include_tests,
+        metrics: RsFileMetrics {
+            approximate: true,
+            ..Default::default()
+        },
+    };
+    scanner.metrics.lint_status = inner_attributes(&tokens)
+        .filter_map(|meta| meta_unsafe_code_lint_status(&meta, false))
+        .max()
+        .unwrap_or_default();
+    scanner.metrics.forbids_unsafe =
+        scanner.metrics.lint_status == LintStatus::Forbid;
+    scanner.scan(&tokens, BodyKind::Code);
+    Ok(scanner.metrics)
+}
+
+/// What the items declared in a brace-delimited group are.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BodyKind {
+    /// Items and statements, where functions are free-standing functions.
+    Code,
+    /// The body of an `impl` or a trait, where functions are methods.
+    ImplOrTrait,
+    /// The body of an `extern` block, where functions and statics are
+    /// foreign items.
+    Foreign,
+}
+
+struct TokenScanner {
+    include_tests: IncludeTests,
+    metrics: RsFileMetrics,
+}
+
+impl TokenScanner {
+    fn scan(&mut self, tokens: &[TokenTree], body_kind: BodyKind) {
+        // Whether the next brace-delimited group is the body of a test, and
+        // the kind of the items inside it
+        let mut skip_next_body = false;
+        let mut next_body_kind = BodyKind::Code;
+        let mut index = 0;
+        while index < tokens.len() {
+            let before = &tokens[..index];
+            match &tokens[index] {
+                TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                    let (meta, len) = match tokens.get(index + 1) {
+                        Some(TokenTree::Punct(bang))
+                            if bang.as_char() == '!' =>
+                        {
+                            (None, 3)
+                        }
+                        Some(TokenTree::Group(group))
+                            if group.delimiter() == Delimiter::Bracket =>
+                        {
+                            (syn::parse2::<syn::Meta>(group.stream()).ok(), 2)
+                        }
+                        _ => (None, 1),
+                    };
+                    if let Some(meta) = meta {
+                        skip_next_body |= IncludeTests::No
+                            == self.include_tests
+                            && is_test_attribute(&meta);
+                        self.metrics.allows_unsafe_locally |=
+                            meta_unsafe_code_lint_status(&meta, false)
+                                == Some(LintStatus::Allow);
+                    }
+                    index += len;
+                    continue;
+                }
+                TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                    skip_next_body = false;
+                    next_body_kind = BodyKind::Code;
+                }
+                TokenTree::Group(group)
+                    if group.delimiter() == Delimiter::Brace =>
+                {
+                    if !skip_next_body {
+                        let stream =
+                            group.stream().into_iter().collect::<Vec<_>>();
+                        self.scan(&stream, next_body_kind);
+                    }
+                    skip_next_body = false;
+                    next_body_kind = BodyKind::Code;
+                }
+                TokenTree::Group(group) => {
+                    let stream = group.stream().into_iter().collect::<Vec<_>>();
+                    self.scan(&stream, BodyKind::Code);
+                }
+                TokenTree::Ident(ident)
+                    if (ident == "impl" || ident == "trait")
+                        && is_item_start(before) =>
+                {
+                    next_body_kind = BodyKind::ImplOrTrait;
+                    let unsafe_span = match before {
+                        [.., TokenTree::Ident(u)] if u == "unsafe" => {
+                            Some(u.span())
+                        }
+                        [.., TokenTree::Ident(u), TokenTree::Ident(a)]
+                            if u == "unsafe" && a == "auto" =>
+                        {
+                            Some(u.span())
+                        }
+                        _ => None,
+                    };
+                    let (counter, kind) = if ident == "impl" {
+                        (
+                            &mut self.metrics.counters.item_impls,
+                            UnsafeKind::Impl,
+                        )
+                    } else {
+                        (
+                            &mut self.metrics.counters.item_traits,
+                            UnsafeKind::Trait,
+                        )
+                    };
+                    counter.count(unsafe_span.is_some());
+                    if let Some(unsafe_span) = unsafe_span {
+                        let end_span = find_fn_body_span(&tokens[index..]);
+                        self.record_unsafe_location(
+                            kind,
+                            join_spans(unsafe_span, end_span),
+                        );
+                    }
+                }
+                TokenTree::Ident(ident)
+                    if ident == "extern"
+                        && precedes_body(&tokens[index + 1..]) =>
+                {
+                    next_body_kind = BodyKind::Foreign;
+                }
+                TokenTree::Ident(ident)
+                    if body_kind == BodyKind::Foreign
+                        && (ident == "fn" || ident == "static") =>
+                {
+                    // Items declared `safe` in an `unsafe extern` block
+                    let is_safe = before
+                        .iter()
+                        .rev()
+                        .take_while(|token| !is_item_end(token))
+                        .any(|token| {
+                            matches!(token, TokenTree::Ident(s) if s == "safe")
+                        });
+                    let counter = if ident == "fn" {
+                        &mut self.metrics.counters.foreign_functions
+                    } else {
+                        &mut self.metrics.counters.foreign_statics
+                    };
+                    counter.count(!is_safe);
+                }
+                TokenTree::Ident(ident)
+                    if ident == "fn" && is_fn_name(&tokens[index + 1..]) =>
+                {
+                    let unsafe_span = find_unsafe_fn_qualifier(before);
+                    let (counter, kind) = match body_kind {
+                        BodyKind::Code | BodyKind::Foreign => (
+                            &mut self.metrics.counters.functions,
+                            UnsafeKind::Function,
+                        ),
+                        BodyKind::ImplOrTrait => (
+                            &mut self.metrics.counters.methods,
+                            UnsafeKind::Method,
+                        ),
+                    };
+                    counter.count(unsafe_span.is_some());
+                    if let Some(unsafe_span) = unsafe_span {
+                        let end_span = find_fn_body_span(&tokens[index..]);
+                        self.record_unsafe_location(
+                            kind,
+                            join_spans(unsafe_span, end_span),
+                        );
+                    }
+                }
+                TokenTree::Ident(ident) if ident == "unsafe" => {
+                    if let Some(TokenTree::Group(group)) = tokens.get(index + 1)
+                    {
+                        if group.delimiter() == Delimiter::Brace {
+                            self.metrics.counters.exprs.count(true);
+                            self.record_unsafe_location(
+                                UnsafeKind::Block,
+                                join_spans(ident.span(), group.span()),
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+    }
+
+    fn record_unsafe_location(&mut self, kind: UnsafeKind, span: Span) {
+        let (start, end) = (span.start(), span.end());
+        self.metrics.unsafe_locations.push(UnsafeLocation {
+            file: Default::default(),
+            start: LineColumn {
+                line: start.line,
+                column: start.column,
+            },
+            end: LineColumn {
+                line: end.line,
+                column: end.column,
+            },
+            kind,
+        });
+    }
+}
+
+/// The inner attributes at the start of a file, such as
+/// `#![forbid(unsafe_code)]`.
+fn inner_attributes(
+    tokens: &[TokenTree],
+) -> impl Iterator<Item = syn::Meta> + '_ {
+    tokens.chunks(3).map_while(|chunk| match chunk {
+        [TokenTree::Punct(hash), TokenTree::Punct(bang), TokenTree::Group(group)]
+            if hash.as_char() == '#'
+                && bang.as_char() == '!'
+                && group.delimiter() == Delimiter::Bracket =>
+        {
+            Some(syn::parse2::<syn::Meta>(group.stream()).ok())
+        }
+        _ => None,
+    })
+    .flatten()
+}
+
+/// `#[test]` and `#[cfg(test)]`
+fn is_test_attribute(meta: &syn::Meta) -> bool {
+    match meta {
+        syn::Meta::List(meta_list) if meta_list.path.is_ident("cfg") => {
+            meta_list_nested(meta_list)
+                .iter()
+                .any(|meta| meta_contains_ident(meta, "test"))
+        }
+        meta => meta_contains_ident(meta, "test"),
+    }
+}
+
+/// Whether the tokens after `extern` are the body of an `extern` block,
+/// optionally preceded by an ABI string.
+fn precedes_body(tokens_after_extern: &[TokenTree]) -> bool {
+    let body = match tokens_after_extern {
+        [TokenTree::Literal(_), body, ..] => body,
+        [body, ..] => body,
+        [] => return false,
+    };
+    matches!(body, TokenTree::Group(group) if group.delimiter() == Delimiter::Brace)
+}
+
+/// Whether the token ends the previous item, such as the `;` of a foreign
+/// function declaration or the attribute of the next one.
+fn is_item_end(token: &TokenTree) -> bool {
+    match token {
+        TokenTree::Punct(punct) => punct.as_char() == ';',
+        TokenTree::Group(group) => group.delimiter() != Delimiter::Parenthesis,
+        _ => false,
+    }
+}
+
+/// Whether an `impl` or `trait` keyword after these tokens starts an item,
+/// rather than being part of a type such as `-> impl Iterator`.
+fn is_item_start(tokens_before: &[TokenTree]) -> bool {
+    match tokens_before {
+        [] => true,
+        [.., TokenTree::Punct(punct)] => punct.as_char() == ';',
+        [.., TokenTree::Group(group)] => group.delimiter() != Delimiter::None,
+        [.., TokenTree::Ident(ident)] => ["auto", "default", "pub", "unsafe"]
+            .iter()
+            .any(|k| ident == k),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod token_scan_tests {
+    use super::*;
+
+    use cargo_geiger_serde::Count;
+    use rstest::*;
+
+    const UNPARSABLE_SRC: &str = "#![deny(unsafe_code)]
+
+pub unsafe fn f() {}
+
+fn g() -> impl Fn() -> u8 {
+    unsafe { f() };
+    || unsafe { 1 } +
+}
+
+struct A;
+
+impl A {
+    unsafe fn m(&self) {}
+    fn n(&self) {}
+}
+
+unsafe impl Send for A {}
+
+#[allow(unsafe_code)]
+unsafe trait T {}
+
+unsafe extern \"C\" {
+    pub fn c();
+    pub safe fn d();
+    static E: u8;
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_1() {
+        unsafe {}
+    }
+}
+";
+
+    #[rstest(
+        input_include_tests,
+        expected_functions,
+        expected_unsafe_exprs,
+        case(IncludeTests::No, Count { safe: 1, unsafe_: 1 }, 2),
+        case(IncludeTests::Yes, Count { safe: 2, unsafe_: 1 }, 3)
+    )]
+    fn find_unsafe_in_tokens_test(
+        input_include_tests: IncludeTests,
+        expected_functions: Count,
+        expected_unsafe_exprs: u64,
+    ) {
+        assert!(syn::parse_file(UNPARSABLE_SRC).is_err());
+
+        let metrics =
+            find_unsafe_in_tokens(UNPARSABLE_SRC, input_include_tests).unwrap();
+
+        assert!(metrics.approximate);
+        assert_eq!(metrics.counters.functions, expected_functions);
+        assert_eq!(
+            metrics.counters.methods,
+            Count {
+                safe: 1,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            metrics.counters.item_impls,
+            Count {
+                safe: 1,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            metrics.counters.item_traits,
+            Count {
+                safe: 0,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            metrics.counters.foreign_functions,
+            Count {
+                safe: 1,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(
+            metrics.counters.foreign_statics,
+            Count {
+                safe: 0,
+                unsafe_: 1
+            }
+        );
+        assert_eq!(metrics.counters.exprs.unsafe_, expected_unsafe_exprs);
+        assert_eq!(metrics.lint_status, LintStatus::Deny);
+        assert!(metrics.allows_unsafe_locally);
+        assert_eq!(
+            metrics.unsafe_locations.len() as u64,
+            4 + expected_unsafe_exprs
+        );
+    }
+
+    #[rstest]
+    fn find_unsafe_in_tokens_test_lex_error() {
+        assert!(find_unsafe_in_tokens("fn f() { \"", IncludeTests::No).is_err());
+    }
+}
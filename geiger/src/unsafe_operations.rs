@@ -0,0 +1,374 @@
+use crate::{path_segments, resolves_to_declared_path, type_name};
+
+use cargo_geiger_serde::UnsafeOperationKinds;
+use proc_macro2::Ident;
+use std::collections::HashSet;
+use syn::ext::IdentExt;
+use syn::visit::{self, Visit};
+use syn::{
+    Expr, Field, ForeignItemFn, ImplItemFn, ItemFn, ItemImpl, ItemMod,
+    ItemTrait, ItemUnion, Local, Member, Pat, PatType, Path, TraitItemFn, Type,
+};
+
+/// Well-known unsafe functions of the standard library, matched on the last
+/// two segments of the path of a call such as `std::ptr::read(p)`.
+const UNSAFE_STD_FUNCTIONS: [(&str, &str); 29] = [
+    ("alloc", "alloc"),
+    ("alloc", "alloc_zeroed"),
+    ("alloc", "dealloc"),
+    ("alloc", "realloc"),
+    ("Box", "from_raw"),
+    ("CStr", "from_ptr"),
+    ("CString", "from_raw"),
+    ("hint", "unreachable_unchecked"),
+    ("mem", "uninitialized"),
+    ("mem", "zeroed"),
+    ("ptr", "copy"),
+    ("ptr", "copy_nonoverlapping"),
+    ("ptr", "drop_in_place"),
+    ("ptr", "read"),
+    ("ptr", "read_unaligned"),
+    ("ptr", "read_volatile"),
+    ("ptr", "replace"),
+    ("ptr", "swap"),
+    ("ptr", "write"),
+    ("ptr", "write_bytes"),
+    ("ptr", "write_unaligned"),
+    ("ptr", "write_volatile"),
+    ("slice", "from_raw_parts"),
+    ("slice", "from_raw_parts_mut"),
+    ("str", "from_utf8_unchecked"),
+    ("str", "from_utf8_unchecked_mut"),
+    ("String", "from_raw_parts"),
+    ("String", "from_utf8_unchecked"),
+    ("Vec", "from_raw_parts"),
+];
+
+/// Methods returning a raw pointer whatever their receiver, such as
+/// `v.as_ptr()`.
+const RAW_POINTER_GETTERS: [&str; 2] = ["as_mut_ptr", "as_ptr"];
+
+/// Methods returning a raw pointer when called on one, such as `p.add(1)`.
+/// Integers have methods of the same names, such as `1u8.add(1)`.
+const RAW_POINTER_ARITHMETIC: [&str; 12] = [
+    "add",
+    "byte_add",
+    "byte_offset",
+    "byte_sub",
+    "cast",
+    "cast_const",
+    "cast_mut",
+    "offset",
+    "sub",
+    "wrapping_add",
+    "wrapping_offset",
+    "wrapping_sub",
+];
+
+/// An operation that requires `unsafe`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnsafeOperation {
+    RawPointerDeref,
+    UnsafeFnCall,
+    UnionFieldAccess,
+    StaticMutAccess,
+    InlineAsm,
+    Transmute,
+}
+
+impl UnsafeOperation {
+    pub fn count(self, kinds: &mut UnsafeOperationKinds) {
+        let counter = match self {
+            UnsafeOperation::RawPointerDeref => &mut kinds.raw_pointer_derefs,
+            UnsafeOperation::UnsafeFnCall => &mut kinds.unsafe_fn_calls,
+            UnsafeOperation::UnionFieldAccess => {
+                &mut kinds.union_field_accesses
+            }
+            UnsafeOperation::StaticMutAccess => &mut kinds.static_mut_accesses,
+            UnsafeOperation::InlineAsm => &mut kinds.inline_asm,
+            UnsafeOperation::Transmute => &mut kinds.transmutes,
+        };
+        *counter += 1;
+    }
+}
+
+/// Names declared in a file, used to recognize the operations requiring
+/// `unsafe` without resolving types. Like the `static mut` items, the
+/// functions are matched by their path in the file, and the other names
+/// regardless of the scope they are declared in.
+#[derive(Debug, Default)]
+pub struct UnsafeOperationNames {
+    /// Paths of the `unsafe fn` items and of the functions of `extern` blocks
+    unsafe_fns: HashSet<Vec<String>>,
+    /// Paths of the `unsafe fn` items of `impl` and `trait` blocks, under the
+    /// name of their type or trait
+    unsafe_assoc_fns: HashSet<Vec<String>>,
+    /// Union types
+    unions: HashSet<String>,
+    /// Parameters, local bindings and fields with a raw pointer type
+    raw_pointers: HashSet<String>,
+    /// Parameters, local bindings and fields with a union type
+    union_values: HashSet<String>,
+}
+
+impl UnsafeOperationNames {
+    pub fn from_file(f: &syn::File) -> Self {
+        let mut collector = NameCollector::default();
+        collector.visit_file(f);
+        let NameCollector {
+            mut names,
+            typed_names,
+            ..
+        } = collector;
+        names.union_values = typed_names
+            .into_iter()
+            .filter(|(_, type_name)| names.unions.contains(type_name))
+            .map(|(name, _)| name)
+            .collect();
+        names
+    }
+
+    /// Whether the type is one of the unions of the file.
+    pub fn is_union_type(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Path(type_path) => path_last_ident(&type_path.path)
+                .is_some_and(|type_name| self.unions.contains(&type_name)),
+            _ => false,
+        }
+    }
+
+    /// Whether the path of a call, made in the inline module at
+    /// `module_path` and within the `impl` or `trait` block of `self_type`,
+    /// refers to an `unsafe fn` of the file or to a well-known unsafe
+    /// function of the standard library. A bare name only refers to
+    /// functions that are not associated with a type.
+    pub fn is_unsafe_fn_path(
+        &self,
+        path: &Path,
+        module_path: &[String],
+        self_type: Option<&str>,
+    ) -> bool {
+        let mut segments = path_segments(path);
+        if let (Some(first), Some(self_type)) =
+            (segments.first_mut(), self_type)
+        {
+            if first == "Self" {
+                *first = self_type.to_owned();
+            }
+        }
+        match segments.as_slice() {
+            [.., parent, name] => {
+                resolves_to_declared_path(
+                    &self.unsafe_fns,
+                    module_path,
+                    &segments,
+                ) || resolves_to_declared_path(
+                    &self.unsafe_assoc_fns,
+                    module_path,
+                    &segments,
+                ) || UNSAFE_STD_FUNCTIONS
+                    .contains(&(parent.as_str(), name.as_str()))
+            }
+            [_] => resolves_to_declared_path(
+                &self.unsafe_fns,
+                module_path,
+                &segments,
+            ),
+            [] => false,
+        }
+    }
+
+    /// Whether the expression is a raw pointer: a cast to a raw pointer type,
+    /// a call to a method returning a raw pointer, or a binding or field
+    /// declared with a raw pointer type.
+    pub fn is_raw_pointer(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Cast(expr_cast) => matches!(*expr_cast.ty, Type::Ptr(_)),
+            Expr::MethodCall(expr_method_call) => {
+                let method = expr_method_call.method.to_string();
+                RAW_POINTER_GETTERS.contains(&method.as_str())
+                    || (RAW_POINTER_ARITHMETIC.contains(&method.as_str())
+                        && self.is_raw_pointer(&expr_method_call.receiver))
+            }
+            Expr::Paren(expr_paren) => self.is_raw_pointer(&expr_paren.expr),
+            other => expr_name(other)
+                .is_some_and(|name| self.raw_pointers.contains(&name)),
+        }
+    }
+
+    /// Whether the expression is a binding or field declared with a union
+    /// type. `self` is not covered, see `GeigerSynVisitor`.
+    pub fn is_union_value(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Paren(expr_paren) => self.is_union_value(&expr_paren.expr),
+            other => expr_name(other)
+                .is_some_and(|name| self.union_values.contains(&name)),
+        }
+    }
+}
+
+/// Whether the path of a call refers to `transmute` or `transmute_copy`.
+pub fn is_transmute_path(path: &Path) -> bool {
+    path_last_ident(path)
+        .is_some_and(|name| name == "transmute" || name == "transmute_copy")
+}
+
+/// Whether the path of a macro refers to `asm!` or `global_asm!`.
+pub fn is_asm_macro_path(path: &Path) -> bool {
+    path_last_ident(path)
+        .is_some_and(|name| name == "asm" || name == "global_asm")
+}
+
+fn path_last_ident(path: &Path) -> Option<String> {
+    path.segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+}
+
+/// The name of a single-segment path such as `p`, or the field name of a
+/// field access such as `self.p`.
+fn expr_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(expr_path) => {
+            expr_path.path.get_ident().map(|ident| ident.to_string())
+        }
+        Expr::Field(expr_field) => match &expr_field.member {
+            Member::Named(ident) => Some(ident.to_string()),
+            Member::Unnamed(_) => None,
+        },
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct NameCollector {
+    names: UnsafeOperationNames,
+    /// Names of the inline modules enclosing the node being visited
+    module_path: Vec<String>,
+    /// The name of the type or trait of the innermost `impl` or `trait`
+    /// block being visited
+    self_type: Option<String>,
+    /// Names declared with a path type, with the last segment of the type
+    typed_names: Vec<(String, String)>,
+}
+
+impl NameCollector {
+    fn insert_unsafe_fn(&mut self, name: &Ident) {
+        let mut path = self.module_path.clone();
+        path.push(name.unraw().to_string());
+        self.names.unsafe_fns.insert(path);
+    }
+
+    fn insert_unsafe_assoc_fn(&mut self, name: &Ident) {
+        let mut path = self.module_path.clone();
+        path.extend(self.self_type.clone());
+        path.push(name.unraw().to_string());
+        self.names.unsafe_assoc_fns.insert(path);
+    }
+
+    fn insert_typed_name(&mut self, name: String, ty: &Type) {
+        match ty {
+            Type::Ptr(_) => {
+                self.names.raw_pointers.insert(name);
+            }
+            Type::Path(type_path) => {
+                if let Some(type_name) = path_last_ident(&type_path.path) {
+                    self.typed_names.push((name, type_name));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for NameCollector {
+    fn visit_item_mod(&mut self, i: &'ast ItemMod) {
+        self.module_path.push(i.ident.unraw().to_string());
+        visit::visit_item_mod(self, i);
+        self.module_path.pop();
+    }
+
+    fn visit_item_impl(&mut self, i: &'ast ItemImpl) {
+        let self_type = self.self_type.replace(type_name(&i.self_ty));
+        visit::visit_item_impl(self, i);
+        self.self_type = self_type;
+    }
+
+    fn visit_item_trait(&mut self, i: &'ast ItemTrait) {
+        let self_type = self.self_type.replace(i.ident.unraw().to_string());
+        visit::visit_item_trait(self, i);
+        self.self_type = self_type;
+    }
+
+    fn visit_item_fn(&mut self, i: &'ast ItemFn) {
+        if i.sig.unsafety.is_some() {
+            self.insert_unsafe_fn(&i.sig.ident);
+        }
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast ImplItemFn) {
+        if i.sig.unsafety.is_some() {
+            self.insert_unsafe_assoc_fn(&i.sig.ident);
+        }
+        visit::visit_impl_item_fn(self, i);
+    }
+
+    fn visit_trait_item_fn(&mut self, i: &'ast TraitItemFn) {
+        if i.sig.unsafety.is_some() {
+            self.insert_unsafe_assoc_fn(&i.sig.ident);
+        }
+        visit::visit_trait_item_fn(self, i);
+    }
+
+    fn visit_foreign_item_fn(&mut self, i: &'ast ForeignItemFn) {
+        self.insert_unsafe_fn(&i.sig.ident);
+        visit::visit_foreign_item_fn(self, i);
+    }
+
+    fn visit_item_union(&mut self, i: &'ast ItemUnion) {
+        self.names.unions.insert(i.ident.to_string());
+        visit::visit_item_union(self, i);
+    }
+
+    fn visit_field(&mut self, i: &'ast Field) {
+        if let Some(ident) = &i.ident {
+            self.insert_typed_name(ident.to_string(), &i.ty);
+        }
+        visit::visit_field(self, i);
+    }
+
+    /// Typed parameters of functions and closures, and typed local bindings
+    fn visit_pat_type(&mut self, i: &'ast PatType) {
+        if let Pat::Ident(pat_ident) = &*i.pat {
+            self.insert_typed_name(pat_ident.ident.to_string(), &i.ty);
+        }
+        visit::visit_pat_type(self, i);
+    }
+
+    /// Local bindings without a type, such as `let p = v.as_ptr();` or
+    /// `let u = IntOrFloat { i: 1 };`
+    fn visit_local(&mut self, i: &'ast Local) {
+        if let (Pat::Ident(pat_ident), Some(init)) = (&i.pat, &i.init) {
+            let name = pat_ident.ident.to_string();
+            match &*init.expr {
+                Expr::Struct(expr_struct) => {
+                    if let Some(type_name) = path_last_ident(&expr_struct.path)
+                    {
+                        self.typed_names.push((name, type_name));
+                    }
+                }
+                Expr::Cast(expr_cast) => {
+                    self.insert_typed_name(name, &expr_cast.ty);
+                }
+                expr @ Expr::MethodCall(_)
+                    if self.names.is_raw_pointer(expr) =>
+                {
+                    self.names.raw_pointers.insert(name);
+                }
+                _ => {}
+            }
+        }
+        visit::visit_local(self, i);
+    }
+}